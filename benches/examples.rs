@@ -0,0 +1,57 @@
+// Times the shipped examples/*.fth with the bytecode evaluator and with the
+// tree-walking one it replaced. The examples print to stdout and the timings
+// go to stderr, so `cargo bench > /dev/null` shows just the table.
+use rforth::Forth;
+use rforth::bytecode::Backend;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+const RUNS: u32 = 500;
+
+// Average time to interpret the whole file in a fresh VM
+fn time_example(source: &str, backend: Backend) -> Duration {
+    let start = Instant::now();
+    for _ in 0..RUNS {
+        let mut vm = Forth::new();
+        vm.set_backend(backend);
+        for line in source.lines() {
+            vm.interpret(line).expect("examples run without errors");
+        }
+    }
+    start.elapsed() / RUNS
+}
+
+fn main() {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("examples");
+    let mut paths: Vec<PathBuf> = fs::read_dir(dir)
+        .expect("examples directory")
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "fth"))
+        .collect();
+    paths.sort();
+
+    let mut rows = Vec::new();
+    for path in paths {
+        let source = fs::read_to_string(&path).unwrap();
+        let tree = time_example(&source, Backend::Tree);
+        let bytecode = time_example(&source, Backend::Bytecode);
+        let name = path.file_name().unwrap().to_string_lossy().into_owned();
+        rows.push((name, tree, bytecode));
+    }
+
+    eprintln!();
+    eprintln!(
+        "{:<16} {:>12} {:>12} {:>8}",
+        "example", "tree", "bytecode", "speedup"
+    );
+    for (name, tree, bytecode) in rows {
+        eprintln!(
+            "{:<16} {:>12.2?} {:>12.2?} {:>7.2}x",
+            name,
+            tree,
+            bytecode,
+            tree.as_secs_f64() / bytecode.as_secs_f64()
+        );
+    }
+}
@@ -0,0 +1,256 @@
+use crate::parser::ForthOp;
+
+// How colon definitions are stored and run
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Backend {
+    #[default]
+    Bytecode, // Flattened into jumps when they are defined
+    Tree, // As parsed, with nested op lists for control structures
+}
+
+// Turn the structured control flow of a definition into a flat list of ops
+// with jumps. Offsets count from the jumping op. Ops that are not part of a
+// well-formed structure, like LOOP without DO, are kept as they are and fail
+// when they run.
+pub fn flatten(ops: &[ForthOp]) -> Vec<ForthOp> {
+    let mut code = Code::default();
+    code.list(ops);
+    code.ops
+}
+
+#[derive(Default)]
+struct Code {
+    ops: Vec<ForthOp>,
+    loops: Vec<(usize, Vec<usize>)>, // Open DO loops: where the DO is, and the LEAVEs to patch at LOOP
+}
+
+impl Code {
+    fn here(&self) -> usize {
+        self.ops.len()
+    }
+
+    // Emit a jump whose target is patched in later
+    fn forward(&mut self, jump: fn(isize) -> ForthOp) -> usize {
+        self.ops.push(jump(0));
+        self.here() - 1
+    }
+
+    // Emit a jump back to `target`
+    fn back(&mut self, jump: fn(isize) -> ForthOp, target: usize) {
+        let offset = target as isize - self.here() as isize;
+        self.ops.push(jump(offset));
+    }
+
+    // Point the forward jump at `at` to the next op emitted
+    fn resolve(&mut self, at: usize) {
+        let offset = (self.here() - at) as isize;
+        match &mut self.ops[at] {
+            ForthOp::Branch(o)
+            | ForthOp::ZeroBranch(o)
+            | ForthOp::DoBranch(o)
+            | ForthOp::QDoBranch(o)
+            | ForthOp::LeaveBranch(o) => *o = offset,
+            op => unreachable!("not a forward jump: {}", op),
+        }
+    }
+
+    // A DO loop closes in the op list that opened it
+    fn list(&mut self, ops: &[ForthOp]) {
+        let outer_loops = self.loops.len();
+        for op in ops {
+            self.op(op, outer_loops);
+        }
+        // DO without LOOP: leave it to fail when it runs
+        while self.loops.len() > outer_loops {
+            let (at, leaves) = self.loops.pop().unwrap();
+            self.ops[at] = match self.ops[at] {
+                ForthOp::QDoBranch(_) => ForthOp::QDo,
+                _ => ForthOp::Do,
+            };
+            for leave in leaves {
+                self.ops[leave] = ForthOp::Leave;
+            }
+        }
+    }
+
+    fn op(&mut self, op: &ForthOp, outer_loops: usize) {
+        match op {
+            ForthOp::IfElse(then_ops, else_ops) => {
+                let if_ = self.forward(ForthOp::ZeroBranch);
+                self.list(then_ops);
+                if else_ops.is_empty() {
+                    self.resolve(if_);
+                } else {
+                    let else_ = self.forward(ForthOp::Branch);
+                    self.resolve(if_);
+                    self.list(else_ops);
+                    self.resolve(else_);
+                }
+            }
+            ForthOp::BeginUntil(body) => {
+                let begin = self.here();
+                self.list(body);
+                self.back(ForthOp::ZeroBranch, begin);
+            }
+            ForthOp::BeginAgain(body) => {
+                let begin = self.here();
+                self.list(body);
+                self.back(ForthOp::Branch, begin);
+            }
+            ForthOp::BeginWhileRepeat(cond, body) => {
+                let begin = self.here();
+                self.list(cond);
+                let while_ = self.forward(ForthOp::ZeroBranch);
+                self.list(body);
+                self.back(ForthOp::Branch, begin);
+                self.resolve(while_);
+            }
+            ForthOp::Case(clauses, default) => {
+                let mut endofs = Vec::new();
+                for (value, body) in clauses {
+                    // OF is OVER = IF DROP, ENDOF jumps to the end
+                    self.list(value);
+                    self.ops.extend([ForthOp::Over, ForthOp::Eq]);
+                    let of = self.forward(ForthOp::ZeroBranch);
+                    self.ops.push(ForthOp::Drop);
+                    self.list(body);
+                    endofs.push(self.forward(ForthOp::Branch));
+                    self.resolve(of);
+                }
+                // No OF matched: ENDCASE drops the selector after the default ops
+                self.list(default);
+                self.ops.push(ForthOp::Drop);
+                for endof in endofs {
+                    self.resolve(endof);
+                }
+            }
+            ForthOp::Do | ForthOp::QDo => {
+                let jump = if *op == ForthOp::Do {
+                    ForthOp::DoBranch
+                } else {
+                    ForthOp::QDoBranch
+                };
+                let do_ = self.forward(jump);
+                self.loops.push((do_, Vec::new()));
+            }
+            ForthOp::Loop | ForthOp::PlusLoop if self.loops.len() > outer_loops => {
+                let (do_, leaves) = self.loops.pop().unwrap();
+                let jump = if *op == ForthOp::Loop {
+                    ForthOp::LoopBranch
+                } else {
+                    ForthOp::PlusLoopBranch
+                };
+                self.back(jump, do_ + 1);
+                self.resolve(do_);
+                for leave in leaves {
+                    self.resolve(leave);
+                }
+            }
+            // LEAVE may sit in an IF or BEGIN inside its loop
+            ForthOp::Leave if !self.loops.is_empty() => {
+                let leave = self.forward(ForthOp::LeaveBranch);
+                self.loops.last_mut().unwrap().1.push(leave);
+            }
+            ForthOp::Does(code) => self.ops.push(ForthOp::Does(flatten(code))),
+            other => self.ops.push(other.clone()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ForthOp::*;
+
+    #[test]
+    fn test_flatten_if_else() {
+        let ops = vec![IfElse(vec![Push(1)], vec![Push(2), Dup])];
+        assert_eq!(
+            flatten(&ops),
+            vec![ZeroBranch(3), Push(1), Branch(3), Push(2), Dup]
+        );
+        let ops = vec![IfElse(vec![Push(1)], vec![]), Drop];
+        assert_eq!(flatten(&ops), vec![ZeroBranch(2), Push(1), Drop]);
+    }
+
+    #[test]
+    fn test_flatten_begin_loops() {
+        let ops = vec![BeginUntil(vec![Dup, OneMinus])];
+        assert_eq!(flatten(&ops), vec![Dup, OneMinus, ZeroBranch(-2)]);
+        let ops = vec![Push(0), BeginAgain(vec![OnePlus])];
+        assert_eq!(flatten(&ops), vec![Push(0), OnePlus, Branch(-1)]);
+        let ops = vec![BeginWhileRepeat(vec![Dup], vec![OneMinus])];
+        assert_eq!(
+            flatten(&ops),
+            vec![Dup, ZeroBranch(3), OneMinus, Branch(-3)]
+        );
+    }
+
+    #[test]
+    fn test_flatten_case() {
+        let ops = vec![Case(vec![(vec![Push(1)], vec![Push(10)])], vec![Push(0)])];
+        assert_eq!(
+            flatten(&ops),
+            vec![
+                Push(1),
+                Over,
+                Eq,
+                ZeroBranch(4),
+                Drop,
+                Push(10),
+                Branch(3),
+                Push(0),
+                Drop,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_flatten_do_loops() {
+        // LEAVE inside an IF jumps past the LOOP of its own loop
+        let ops = vec![
+            Do,
+            I,
+            IfElse(vec![Leave], vec![]),
+            QDo,
+            J,
+            PlusLoop,
+            Loop,
+            Drop,
+        ];
+        assert_eq!(
+            flatten(&ops),
+            vec![
+                DoBranch(8),
+                I,
+                ZeroBranch(2),
+                LeaveBranch(5),
+                QDoBranch(3),
+                J,
+                PlusLoopBranch(-1),
+                LoopBranch(-6),
+                Drop,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_flatten_keeps_unmatched_loops() {
+        let ops = vec![Do, Leave];
+        assert_eq!(flatten(&ops), vec![Do, Leave]);
+        // The LOOP in the IF does not close the DO outside it
+        let ops = vec![Do, IfElse(vec![Loop], vec![])];
+        assert_eq!(flatten(&ops), vec![Do, ZeroBranch(2), Loop]);
+        let ops = vec![Loop, Leave];
+        assert_eq!(flatten(&ops), vec![Loop, Leave]);
+    }
+
+    #[test]
+    fn test_flatten_does() {
+        let ops = vec![Create(None), Does(vec![IfElse(vec![Dup], vec![])])];
+        assert_eq!(
+            flatten(&ops),
+            vec![Create(None), Does(vec![ZeroBranch(2), Dup])]
+        );
+    }
+}
@@ -1,18 +1,59 @@
+use crate::bytecode::{self, Backend}; // Flat definitions
+use crate::format_ops; // Number formatting and pictured output
+use crate::forth::{BASE_ADDR, Forth, STATE_ADDR};
+use crate::io_ops; // Character input and output
+use crate::memory_ops; // Data space access
 use crate::number_ops; // Import arithmetic and comparison ops
-use crate::parser::ForthOp;
+use crate::parser::{CONTROL_WORDS, ForthOp, ParseError, compile_word, with_compiler, word_name};
 use crate::stack_ops; // Import the stack_ops module
-use std::collections::HashMap; // Import HashMap
-use std::fmt;
+use crate::token::Location;
 
-// Import ParseError only for tests
-#[cfg(test)]
-use crate::parser::ParseError;
+use std::fmt;
+use std::io;
+use std::rc::Rc;
 
 // Define a structure for dictionary entries
 #[derive(Debug, Clone)]
 pub struct DictEntry {
-    pub body: Vec<ForthOp>,
+    pub name: String,
+    pub body: Rc<[ForthOp]>,
     pub immediate: bool,
+    pub kind: WordKind,
+}
+
+// What a dictionary entry does when it is executed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WordKind {
+    Colon,                 // Runs its body (colon definitions and native words)
+    Variable(i64),         // VARIABLE / 2VARIABLE: pushes the address of its cells
+    Constant(i64),         // CONSTANT: pushes its value
+    TwoConstant(i64, i64), // 2CONSTANT: pushes both values
+    Value(i64),            // VALUE: pushes the cell at this address, which TO updates
+    Created(i64),          // CREATE: pushes its data-field address, then runs the DOES> body
+    Deferred(Option<i64>), // DEFER: runs the execution token set by IS
+}
+
+// Signature of host functions registered as native words
+pub type NativeFn = dyn Fn(&mut Forth) -> Result<(), EvalError>;
+
+// A word implemented by a Rust closure rather than by Forth ops
+#[derive(Clone)]
+pub struct NativeWord {
+    pub name: String,
+    pub func: Rc<NativeFn>,
+}
+
+impl fmt::Debug for NativeWord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "NativeWord({})", self.name)
+    }
+}
+
+// Two native words are equal only if they share the same closure
+impl PartialEq for NativeWord {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.func, &other.func)
+    }
 }
 
 #[derive(Debug, PartialEq)]
@@ -20,10 +61,27 @@ pub enum EvalError {
     StackUnderflow,
     DivisionByZero,
     UnknownWord(String),
-    CompileOnlyWord(String),  // e.g. IF, THEN, DO, LOOP used at runtime
-    LoopStackUnderflow,       // Added: Trying to use LOOP/I without DO
+    CompileOnlyWord(String),    // e.g. IF, THEN, DO, LOOP used at runtime
+    LoopStackUnderflow,         // Added: Trying to use LOOP/I without DO
     ControlStructureMismatch, // Added: DO without matching LOOP at runtime (should be caught by parser ideally)
     NoRecentDefinition,       // Added: When IMMEDIATE is used but no recent definition exists
+    InvalidAddress(i64),      // Memory access or ALLOT outside the data space
+    NotAValue(String),        // TO applied to a word that was not defined by VALUE
+    NotCreated(String),       // DOES> when the latest word was not made by CREATE
+    MissingName(String),      // A defining word ran with no name left in the input
+    ReturnStackUnderflow,     // R>, R@ etc. with nothing on the word's return stack frame
+    ReturnStackImbalance,     // A word returned without removing what it put on the return stack
+    InvalidExecutionToken(i64), // EXECUTE or IS with a number that is not an execution token
+    NotDeferred(String),      // IS or ACTION-OF applied to a word not defined by DEFER
+    DeferNotSet(String),      // A deferred word ran before IS gave it an action
+    Compile(ParseError),      // A POSTPONEd word failed to compile, e.g. THEN with no open IF
+    PicturedOutputOverflow,   // <# ... #> text longer than its buffer
+    Overflow,                 // An arithmetic result too big for a cell, in checked mode
+    ReturnStackOverflow,      // Words nested deeper than the VM's maximum depth
+    Throw(i64),               // THROW with a code that no other error has
+    Abort,                    // ABORT
+    AbortQuote(String),       // ABORT" with its message
+    Io(String),               // Writing output failed, e.g. to a closed pipe
 }
 
 impl fmt::Display for EvalError {
@@ -38,25 +96,166 @@ impl fmt::Display for EvalError {
                 write!(f, "Control structure mismatch during execution")
             }
             EvalError::NoRecentDefinition => write!(f, "No recent definition for IMMEDIATE"),
+            EvalError::InvalidAddress(addr) => write!(f, "Invalid memory address: {}", addr),
+            EvalError::NotAValue(s) => write!(f, "Not a VALUE: {}", s),
+            EvalError::NotCreated(s) => write!(f, "DOES> needs a CREATEd word: {}", s),
+            EvalError::MissingName(s) => write!(f, "Expected a name after {}", s),
+            EvalError::ReturnStackUnderflow => write!(f, "Return stack underflow"),
+            EvalError::ReturnStackImbalance => write!(f, "Return stack imbalance at word exit"),
+            EvalError::InvalidExecutionToken(xt) => write!(f, "Invalid execution token: {}", xt),
+            EvalError::NotDeferred(s) => write!(f, "Not a deferred word: {}", s),
+            EvalError::DeferNotSet(s) => write!(f, "Deferred word has no action: {}", s),
+            EvalError::Compile(e) => write!(f, "Compile error: {:?}", e),
+            EvalError::PicturedOutputOverflow => write!(f, "Pictured numeric output overflow"),
+            EvalError::Overflow => write!(f, "Arithmetic overflow"),
+            EvalError::ReturnStackOverflow => write!(f, "Return stack overflow"),
+            EvalError::Throw(code) => write!(f, "Uncaught exception: {}", code),
+            EvalError::Abort => write!(f, "Aborted"),
+            EvalError::AbortQuote(message) => write!(f, "{}", message),
+            EvalError::Io(message) => write!(f, "I/O error: {}", message),
         }
     }
 }
 
-// Helper function to find the matching LOOP/THEN for DO/IF
-// Returns the index *after* the matching LOOP/THEN
-fn find_matching_end(
-    ops: &[ForthOp],
-    start_idx: usize,
-    open_op: ForthOp, // Pass by value
-    close_op: ForthOp,
-) -> Result<usize, EvalError> {
+impl From<io::Error> for EvalError {
+    fn from(error: io::Error) -> Self {
+        EvalError::Io(error.to_string())
+    }
+}
+
+impl EvalError {
+    // The standard throw code CATCH pushes for this error
+    pub fn code(&self) -> i64 {
+        match self {
+            EvalError::Abort => -1,
+            EvalError::AbortQuote(_) => -2,
+            EvalError::StackUnderflow => -4,
+            EvalError::ReturnStackOverflow => -5,
+            EvalError::ReturnStackUnderflow => -6,
+            EvalError::InvalidAddress(_) => -9,
+            EvalError::DivisionByZero => -10,
+            EvalError::Overflow => -11,
+            EvalError::UnknownWord(_) => -13,
+            EvalError::CompileOnlyWord(_) => -14,
+            EvalError::MissingName(_) => -16,
+            EvalError::PicturedOutputOverflow => -17,
+            EvalError::NoRecentDefinition | EvalError::DeferNotSet(_) => -21,
+            EvalError::ControlStructureMismatch => -22,
+            EvalError::InvalidExecutionToken(_) => -24,
+            EvalError::ReturnStackImbalance => -25,
+            EvalError::LoopStackUnderflow => -26,
+            EvalError::NotCreated(_) => -31,
+            EvalError::NotAValue(_) | EvalError::NotDeferred(_) => -32,
+            EvalError::Io(_) => -37,
+            EvalError::Compile(e) => match e {
+                ParseError::UnknownWord(_) => -13,
+                ParseError::ControlWordOutsideDefinition(_) => -14,
+                ParseError::ExpectedWordName => -16,
+                ParseError::StringTooLong => -18,
+                ParseError::MalformedNumber(_) => -24,
+                ParseError::NestedDefinitionNotSupported => -29,
+                _ => -22,
+            },
+            EvalError::Throw(code) => *code,
+        }
+    }
+
+    // The error THROW raises for a code: the error it stands for if there is
+    // exactly one, so an uncaught -4 THROW reads like a stack underflow
+    pub fn from_code(code: i64) -> EvalError {
+        match code {
+            -1 => EvalError::Abort,
+            -4 => EvalError::StackUnderflow,
+            -5 => EvalError::ReturnStackOverflow,
+            -6 => EvalError::ReturnStackUnderflow,
+            -10 => EvalError::DivisionByZero,
+            -11 => EvalError::Overflow,
+            -17 => EvalError::PicturedOutputOverflow,
+            -22 => EvalError::ControlStructureMismatch,
+            -25 => EvalError::ReturnStackImbalance,
+            -26 => EvalError::LoopStackUnderflow,
+            _ => EvalError::Throw(code),
+        }
+    }
+}
+
+// How many words a backtrace names before it just counts the rest
+const MAX_TRACE: usize = 16;
+
+// A word that was running when an error escaped eval: its name, None for the
+// ops given to eval, and where the op it was running was read, if known
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceEntry {
+    pub word: Option<String>,
+    pub at: Option<Location>,
+}
+
+// The words an error escaped from, innermost first, and how many more there
+// were beyond MAX_TRACE
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Backtrace {
+    pub entries: Vec<TraceEntry>,
+    pub more: usize,
+}
+
+impl Backtrace {
+    // True if the error happened inside a word rather than in the ops given to eval
+    pub fn in_word(&self) -> bool {
+        self.entries
+            .first()
+            .is_some_and(|entry| entry.word.is_some())
+    }
+}
+
+// Reads as the end of the error message, like
+// ` in SQUARE (called from TEST at file.fth:12:5, called at file.fth:20:1)`
+impl fmt::Display for Backtrace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Some((innermost, callers)) = self.entries.split_first() else {
+            return Ok(());
+        };
+        if let Some(word) = &innermost.word {
+            write!(f, " in {}", word)?;
+        }
+        if let Some(at) = &innermost.at {
+            write!(f, " at {}", at)?;
+        }
+        let mut callers: Vec<String> = callers
+            .iter()
+            .filter_map(|caller| match (&caller.word, &caller.at) {
+                (Some(word), Some(at)) => Some(format!("called from {} at {}", word, at)),
+                (Some(word), None) => Some(format!("called from {}", word)),
+                (None, Some(at)) => Some(format!("called at {}", at)),
+                (None, None) => None,
+            })
+            .collect();
+        if self.more > 0 {
+            callers.push(format!("and {} more", self.more));
+        }
+        if !callers.is_empty() {
+            write!(f, " ({})", callers.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+fn is_do(op: &ForthOp) -> bool {
+    matches!(op, ForthOp::Do | ForthOp::QDo)
+}
+
+fn is_loop(op: &ForthOp) -> bool {
+    matches!(op, ForthOp::Loop | ForthOp::PlusLoop)
+}
+
+// Helper function to find the matching LOOP/+LOOP for DO/?DO
+// Returns the index *after* the matching LOOP/+LOOP
+fn find_matching_end(ops: &[ForthOp], start_idx: usize) -> Result<usize, EvalError> {
     let mut depth = 1;
     let mut current_idx = start_idx + 1;
     while current_idx < ops.len() {
-        // Compare enum variants directly (since they derive PartialEq)
-        if ops[current_idx] == open_op {
+        if is_do(&ops[current_idx]) {
             depth += 1;
-        } else if ops[current_idx] == close_op {
+        } else if is_loop(&ops[current_idx]) {
             depth -= 1;
             if depth == 0 {
                 return Ok(current_idx + 1); // Return index *after* the closing op
@@ -67,216 +266,970 @@ fn find_matching_end(
     Err(EvalError::ControlStructureMismatch) // Should be caught by parser, but safeguard
 }
 
-// Modify eval to accept the dictionary AND a loop control stack
-pub fn eval(
-    ops: &[ForthOp],
-    stack: &mut Vec<i64>,
-    dictionary: &mut HashMap<String, DictEntry>,
-    loop_control_stack: &mut Vec<(usize, i64, i64)>, // (loop_start_idx_after_do, current_index, limit)
-    latest_word: &mut Option<String>,                // Track the latest defined word for IMMEDIATE
+// +LOOP terminates when the index crosses the boundary between limit-1 and
+// limit, in either direction. Offsets from the limit wrap like the index does.
+fn plus_loop_done(index: i64, limit: i64, step: i64) -> bool {
+    let old = index.wrapping_sub(limit);
+    let new = old.wrapping_add(step);
+    (old ^ new) & (old ^ step) < 0
+}
+
+// Number of cells the running word has on the return stack
+fn frame_depth(vm: &Forth) -> usize {
+    vm.return_stack.len() - vm.return_base
+}
+
+// Pop from the running word's return stack frame
+fn r_pop(vm: &mut Forth) -> Result<i64, EvalError> {
+    if frame_depth(vm) == 0 {
+        return Err(EvalError::ReturnStackUnderflow);
+    }
+    Ok(vm.return_stack.pop().unwrap())
+}
+
+// Read the cell `depth` below the top of the running word's frame
+fn r_peek(vm: &Forth, depth: usize) -> Result<i64, EvalError> {
+    if frame_depth(vm) <= depth {
+        return Err(EvalError::ReturnStackUnderflow);
+    }
+    Ok(vm.return_stack[vm.return_stack.len() - 1 - depth])
+}
+
+// Loop parameters are two cells on the return stack: the limit, then the index
+// on top. Push the index of the loop `depth` levels out (0 = innermost, for I).
+fn push_loop_index(vm: &mut Forth, depth: usize) -> Result<(), EvalError> {
+    let index = r_peek(vm, 2 * depth).map_err(|_| EvalError::LoopStackUnderflow)?;
+    vm.stack.push(index);
+    Ok(())
+}
+
+// Add step to the index of the innermost loop, for LOOP (plus = false) and
+// +LOOP. Returns true, after dropping the loop's parameters, once it is done.
+fn step_loop(vm: &mut Forth, step: i64, plus: bool) -> Result<bool, EvalError> {
+    if frame_depth(vm) < 2 {
+        return Err(EvalError::LoopStackUnderflow);
+    }
+    let len = vm.return_stack.len();
+    let (limit, current_index) = (vm.return_stack[len - 2], vm.return_stack[len - 1]);
+    let done = if plus {
+        plus_loop_done(current_index, limit, step)
+    } else {
+        current_index.wrapping_add(1) >= limit
+    };
+    vm.return_stack[len - 1] = current_index.wrapping_add(step); // Increment index
+    if done {
+        drop_loop(vm)?;
+    }
+    Ok(done)
+}
+
+// Drop the innermost loop's parameters, for UNLOOP and LEAVE
+fn drop_loop(vm: &mut Forth) -> Result<(), EvalError> {
+    if frame_depth(vm) < 2 {
+        return Err(EvalError::LoopStackUnderflow);
+    }
+    vm.return_stack.truncate(vm.return_stack.len() - 2);
+    Ok(())
+}
+
+// The name a parsing word acts on: known already if the word was used
+// interpretively, otherwise read from the input when it runs
+fn parsed_name(
+    name: &Option<String>,
+    vm: &mut Forth,
+    parsing_word: &str,
+) -> Result<String, EvalError> {
+    match name {
+        Some(name) => Ok(name.clone()),
+        None => match word_name(vm.input.next()) {
+            Some(name) => Ok(name.to_uppercase()),
+            None => Err(EvalError::MissingName(parsing_word.to_string())),
+        },
+    }
+}
+
+// Add a word with no body to the dictionary, for the defining words
+fn define_kind(vm: &mut Forth, name: &str, kind: WordKind) {
+    vm.add_entry(DictEntry {
+        name: name.to_string(),
+        body: Rc::from([]),
+        immediate: false,
+        kind,
+    });
+}
+
+// The deferred word `name`, for IS and ACTION-OF
+fn deferred_mut<'a>(vm: &'a mut Forth, name: &str) -> Result<&'a mut DictEntry, EvalError> {
+    match vm.lookup_mut(name) {
+        Some(entry) if matches!(entry.kind, WordKind::Deferred(_)) => Ok(entry),
+        Some(_) => Err(EvalError::NotDeferred(name.to_string())),
+        None => Err(EvalError::UnknownWord(name.to_string())),
+    }
+}
+
+// Reserve `count` zeroed cells at the next aligned address and return it
+fn allot_cells(vm: &mut Forth, count: i64) -> Result<i64, EvalError> {
+    memory_ops::align(&mut vm.memory)?;
+    let addr = vm.memory.len() as i64;
+    memory_ops::allot_bytes(&mut vm.memory, count * memory_ops::CELL_SIZE)?;
+    Ok(addr)
+}
+
+// Which op list a frame runs
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum List {
+    Body {
+        word: Option<usize>,
+        base: usize,
+        caller_base: usize,
+    }, // A whole definition: its execution token, and where its return stack frame starts
+    Then,
+    Else,
+    Until,            // BEGIN ... UNTIL
+    Again,            // BEGIN ... AGAIN
+    While,            // BEGIN ... WHILE
+    Repeat,           // WHILE ... REPEAT
+    CaseValue(usize), // The ops before the nth OF
+    CaseBody(usize),  // The nth OF ... ENDOF
+    CaseDefault,      // After the last ENDOF
+    // Runs nothing itself: the word CATCH runs is called above it. Holds what
+    // to restore when an error unwinds to it.
+    Catch {
+        depth: usize,
+        return_depth: usize,
+        return_base: usize,
+    },
+}
+
+// An entry on the inner interpreter's own return stack: an op list and the
+// next op to run in it. Control structures get frames of their own and find
+// their ops again from the definition they are nested in.
+#[derive(Debug)]
+struct Frame {
+    root: Option<Rc<[ForthOp]>>, // The definition a Body frame runs; None for the ops given to eval
+    level: usize,                // How many frames down the Body frame of this definition is
+    list: List,
+    ip: usize,                  // The next op to run
+    loops: Vec<(usize, usize)>, // DO loops entered in this list: the op after DO, the op after LOOP
+    calls: usize,               // Body frames at or below this one
+}
+
+impl Frame {
+    fn new(root: Option<Rc<[ForthOp]>>, level: usize, list: List) -> Self {
+        Frame {
+            root,
+            level,
+            list,
+            ip: 0,
+            loops: Vec::new(),
+            calls: 1,
+        }
+    }
+}
+
+// Only calls count against max_depth. The frames control structures add are
+// bounded by how deeply a definition nests them, so words recurse as deep
+// with either backend.
+fn push_frame(vm: &Forth, frames: &mut Vec<Frame>, mut frame: Frame) -> Result<(), EvalError> {
+    let below = frames.last().map_or(0, |frame| frame.calls);
+    frame.calls = below + usize::from(matches!(frame.list, List::Body { .. }));
+    if frame.calls > vm.max_depth {
+        return Err(EvalError::ReturnStackOverflow);
+    }
+    frames.push(frame);
+    Ok(())
+}
+
+// Start running the body of the definition `word` in a new return stack frame
+fn call(
+    body: Rc<[ForthOp]>,
+    word: Option<usize>,
+    vm: &mut Forth,
+    frames: &mut Vec<Frame>,
 ) -> Result<(), EvalError> {
-    let mut idx = 0;
-    while idx < ops.len() {
-        let op = &ops[idx];
-        let mut next_idx = idx + 1; // Default: move to the next instruction
-
-        // println!("DEBUG: Executing {:?} at index {}, Stack: {:?}, LoopStack: {:?}", op, idx, stack, loop_control_stack); // Debugging
-
-        match op {
-            // Simple ops that just execute and move to the next instruction
-            ForthOp::Push(i) => stack.push(*i),
-            ForthOp::Add => number_ops::add(stack)?,
-            ForthOp::Subtract => number_ops::subtract(stack)?,
-            ForthOp::Multiply => number_ops::multiply(stack)?,
-            ForthOp::Divide => number_ops::divide(stack)?,
-            ForthOp::Mod => number_ops::mod_op(stack)?,
-            ForthOp::Eq => number_ops::eq(stack)?,
-            ForthOp::Lt => number_ops::lt(stack)?,
-            ForthOp::Gt => number_ops::gt(stack)?,
-            ForthOp::Dup => stack_ops::dup(stack)?,
-            ForthOp::Drop => stack_ops::drop_(stack)?,
-            ForthOp::Swap => stack_ops::swap(stack)?,
-            ForthOp::Over => stack_ops::over(stack)?,
-            ForthOp::Rot => stack_ops::rot(stack)?,
-            ForthOp::QDup => stack_ops::q_dup(stack)?,
-            ForthOp::TwoDup => stack_ops::two_dup(stack)?,
-            ForthOp::TwoDrop => stack_ops::two_drop(stack)?,
-            ForthOp::TwoSwap => stack_ops::two_swap(stack)?,
-            ForthOp::TwoOver => stack_ops::two_over(stack)?,
-            ForthOp::MinusRot => stack_ops::minus_rot(stack)?,
-            ForthOp::Print => {
-                let top = stack.pop().ok_or(EvalError::StackUnderflow)?;
-                println!("{} ", top);
+    let base = vm.return_stack.len();
+    let list = List::Body {
+        word,
+        base,
+        caller_base: vm.return_base,
+    };
+    push_frame(vm, frames, Frame::new(Some(body), 0, list))?;
+    vm.return_base = base;
+    Ok(())
+}
+
+// Start running one of the op lists nested in the op just executed
+fn nest(vm: &Forth, frames: &mut Vec<Frame>, list: List) -> Result<(), EvalError> {
+    let level = frames.last().map_or(0, |frame| frame.level + 1);
+    push_frame(vm, frames, Frame::new(None, level, list))
+}
+
+// Return from the innermost definition, for EXIT, DOES> and the end of its
+// body. Returns true if that was the ops given to eval, which are then done.
+fn return_from_body(vm: &mut Forth, frames: &mut Vec<Frame>) -> Result<bool, EvalError> {
+    let index = frames
+        .iter()
+        .rposition(|frame| matches!(frame.list, List::Body { .. }))
+        .unwrap_or(0);
+    if index == 0 {
+        return Ok(true);
+    }
+    // The word must leave the return stack as it found it
+    if frame_depth(vm) != 0 {
+        return Err(EvalError::ReturnStackImbalance);
+    }
+    if let List::Body { caller_base, .. } = frames[index].list {
+        vm.return_base = caller_base;
+    }
+    frames.truncate(index);
+    Ok(false)
+}
+
+// LEAVE: drop the innermost DO loop and continue after its LOOP, closing any
+// control structures inside the loop on the way
+fn leave(vm: &mut Forth, frames: &mut Vec<Frame>) -> Result<(), EvalError> {
+    while let Some(frame) = frames.last_mut() {
+        if let Some((_, end)) = frame.loops.pop() {
+            frame.ip = end;
+            return drop_loop(vm);
+        }
+        if matches!(frame.list, List::Body { .. }) {
+            break;
+        }
+        frames.pop();
+    }
+    // No DO loop to leave in this definition
+    Err(EvalError::LoopStackUnderflow)
+}
+
+// What happens when the top frame runs out of ops. Returns true once the
+// ops given to eval are done.
+fn end_of_list(
+    parent: Option<&ForthOp>,
+    vm: &mut Forth,
+    frames: &mut Vec<Frame>,
+) -> Result<bool, EvalError> {
+    let frame = frames.last_mut().unwrap();
+    match frame.list {
+        List::Body { .. } => return return_from_body(vm, frames),
+        List::Until => {
+            let flag = vm.stack.pop().ok_or(EvalError::StackUnderflow)?;
+            if flag != 0 {
+                frames.pop();
+            } else {
+                frame.ip = 0;
             }
-            ForthOp::PrintStack => {
-                print!("Stack: <{}> ", stack.len());
-                for item in stack.iter() {
-                    print!("{} ", item);
-                }
-                println!();
+        }
+        List::Again => frame.ip = 0,
+        List::While => {
+            let flag = vm.stack.pop().ok_or(EvalError::StackUnderflow)?;
+            if flag == 0 {
+                frames.pop();
+            } else {
+                frame.list = List::Repeat;
+                frame.ip = 0;
+            }
+        }
+        List::Repeat => {
+            frame.list = List::While;
+            frame.ip = 0;
+        }
+        List::CaseValue(n) => {
+            // OF compares its value with the selector underneath
+            let value = vm.stack.pop().ok_or(EvalError::StackUnderflow)?;
+            let selector = *vm.stack.last().ok_or(EvalError::StackUnderflow)?;
+            let Some(ForthOp::Case(clauses, _)) = parent else {
+                return Err(EvalError::ControlStructureMismatch);
+            };
+            frame.list = if value == selector {
+                vm.stack.pop();
+                List::CaseBody(n)
+            } else if n + 1 < clauses.len() {
+                List::CaseValue(n + 1)
+            } else {
+                // No OF matched: the default ops see the selector, which ENDCASE drops
+                List::CaseDefault
+            };
+            frame.ip = 0;
+        }
+        List::CaseDefault => {
+            vm.stack.pop().ok_or(EvalError::StackUnderflow)?;
+            frames.pop();
+        }
+        List::Catch { .. } => {
+            // The word returned normally
+            vm.stack.push(0);
+            frames.pop();
+        }
+        List::Then | List::Else | List::CaseBody(_) => {
+            frames.pop();
+        }
+    }
+    Ok(false)
+}
+
+// The ops a frame runs, found by following the nesting ops down from the
+// Body frame that is first in `frames`, along with the op they are nested in
+fn op_list<'a>(
+    root: &'a [ForthOp],
+    frames: &[Frame],
+) -> Result<(&'a [ForthOp], Option<&'a ForthOp>), EvalError> {
+    let mut ops = root;
+    let mut parent = None;
+    for pair in frames.windows(2) {
+        let op = &ops[pair[0].ip - 1];
+        ops = match (pair[1].list, op) {
+            (List::Then, ForthOp::IfElse(then_ops, _)) => then_ops.as_slice(),
+            (List::Else, ForthOp::IfElse(_, else_ops)) => else_ops.as_slice(),
+            (List::Until, ForthOp::BeginUntil(body)) => body.as_slice(),
+            (List::Again, ForthOp::BeginAgain(body)) => body.as_slice(),
+            (List::While, ForthOp::BeginWhileRepeat(cond, _)) => cond.as_slice(),
+            (List::Repeat, ForthOp::BeginWhileRepeat(_, body)) => body.as_slice(),
+            (List::CaseValue(n), ForthOp::Case(clauses, _)) => clauses[n].0.as_slice(),
+            (List::CaseBody(n), ForthOp::Case(clauses, _)) => clauses[n].1.as_slice(),
+            (List::CaseDefault, ForthOp::Case(_, default)) => default.as_slice(),
+            _ => return Err(EvalError::ControlStructureMismatch),
+        };
+        parent = Some(op);
+    }
+    Ok((ops, parent))
+}
+
+// Start running the word xt: words with a body get a frame, the others just
+// push their data
+fn enter(mut xt: usize, vm: &mut Forth, frames: &mut Vec<Frame>) -> Result<(), EvalError> {
+    let mut deferrals = 0;
+    loop {
+        let entry = &vm.entries[xt];
+        match entry.kind {
+            WordKind::Colon => call(entry.body.clone(), Some(xt), vm, frames)?,
+            WordKind::Variable(addr) => vm.stack.push(addr),
+            WordKind::Constant(x) => vm.stack.push(x),
+            WordKind::TwoConstant(x1, x2) => {
+                vm.stack.push(x1);
+                vm.stack.push(x2);
             }
-            // Updated to use Define with immediate flag and store in DictEntry
-            ForthOp::Define(name, body, immediate) => {
-                let entry = DictEntry {
-                    body: body.clone(),
-                    immediate: *immediate,
-                };
-                dictionary.insert(name.clone(), entry);
-                *latest_word = Some(name.clone()); // Update latest word for IMMEDIATE
+            WordKind::Value(addr) => {
+                vm.stack.push(addr);
+                memory_ops::fetch(&mut vm.stack, &vm.memory)?;
             }
-            ForthOp::Immediate => {
-                if let Some(word) = latest_word.clone() {
-                    if let Some(entry) = dictionary.get_mut(&word) {
-                        entry.immediate = true;
-                    }
-                } else {
-                    return Err(EvalError::NoRecentDefinition);
-                }
+            WordKind::Created(addr) => {
+                let body = entry.body.clone();
+                vm.stack.push(addr);
+                call(body, Some(xt), vm, frames)?;
             }
-            ForthOp::I => {
-                let (_, current_index, _) = loop_control_stack
-                    .last()
-                    .ok_or(EvalError::LoopStackUnderflow)?;
-                stack.push(*current_index);
+            WordKind::Deferred(Some(action)) => {
+                // Its action runs in its place. A chain of deferred words
+                // counts against the depth limit like nested calls.
+                deferrals += 1;
+                if deferrals >= vm.max_depth {
+                    return Err(EvalError::ReturnStackOverflow);
+                }
+                vm.entry(action)?;
+                xt = action as usize;
+                continue;
             }
+            WordKind::Deferred(None) => return Err(EvalError::DeferNotSet(entry.name.clone())),
+        }
+        return Ok(());
+    }
+}
+
+// Run a dictionary entry, as an immediate word while compiling
+pub(crate) fn execute(xt: usize, vm: &mut Forth) -> Result<(), EvalError> {
+    run(&[], vm, |vm, frames| enter(xt, vm, frames))
+}
+
+// Execute ops against the VM's stack, dictionary and return stack
+pub fn eval(ops: &[ForthOp], vm: &mut Forth) -> Result<(), EvalError> {
+    run(ops, vm, |_, _| Ok(()))
+}
+
+// The inner interpreter. Instead of calling itself for every word and
+// control structure it keeps a stack of frames, so how deep words can nest
+// is up to the VM's max_depth rather than the Rust stack. `start` may push
+// frames to run before `ops`.
+fn run(
+    ops: &[ForthOp],
+    vm: &mut Forth,
+    start: impl FnOnce(&mut Forth, &mut Vec<Frame>) -> Result<(), EvalError>,
+) -> Result<(), EvalError> {
+    let caller_base = vm.return_base;
+    let list = List::Body {
+        word: None,
+        base: caller_base,
+        caller_base,
+    };
+    let mut frames = vec![Frame::new(None, 0, list)];
+    // The ops as a body RECURSE can call, made the first time one does
+    let mut ops_body = None;
+    let mut result =
+        start(vm, &mut frames).and_then(|()| run_frames(ops, &mut ops_body, vm, &mut frames));
+    // An error inside CATCH carries on after it
+    while let Err(e) = &result
+        && catch(e, vm, &mut frames)
+    {
+        result = run_frames(ops, &mut ops_body, vm, &mut frames);
+    }
+    if result.is_err() {
+        vm.backtrace = backtrace(ops, vm, &frames);
+        // Unwinding: discard whatever the words that failed left on the return stack
+        let called = frames[1..].iter().find_map(|frame| match frame.list {
+            List::Body { base, .. } => Some(base),
+            _ => None,
+        });
+        if let Some(base) = called {
+            vm.return_stack.truncate(base);
+        }
+        vm.return_base = caller_base;
+    }
+    result
+}
 
-            // Ops involving recursive calls or jumps
-            ForthOp::Word(s) => {
-                let wl = s.to_lowercase();
-                if ["if", "else", "then", "do", "loop", "i"].contains(&wl.as_str()) {
-                    return Err(EvalError::CompileOnlyWord(s.clone()));
-                }
-                let upper_s = s.to_uppercase();
-                if let Some(dict_entry) = dictionary.get(&upper_s) {
-                    let ops_to_run = dict_entry.body.clone();
-                    eval(
-                        &ops_to_run,
-                        stack,
-                        dictionary,
-                        loop_control_stack,
-                        latest_word,
-                    )?;
-                } else {
-                    return Err(EvalError::UnknownWord(s.clone()));
-                }
-                // next_idx remains idx + 1
+// The words running when an error escaped, from the frames it left behind.
+// The op each one was running is the one before the ip of the last frame
+// nested in its Body frame.
+fn backtrace(ops: &[ForthOp], vm: &Forth, frames: &[Frame]) -> Backtrace {
+    let mut trace = Backtrace::default();
+    let mut end = frames.len();
+    for (index, frame) in frames.iter().enumerate().rev() {
+        let List::Body { word, .. } = frame.list else {
+            if frame.level == 0 {
+                end = index; // A CATCH frame
+            }
+            continue;
+        };
+        let top = end - 1;
+        end = index;
+        if trace.entries.len() == MAX_TRACE {
+            trace.more += 1;
+            continue;
+        }
+        // An op like IF has no location of its own, so the nearest frame
+        // nested in the definition that ran an op with one gives it
+        let root = frame.root.as_deref().unwrap_or(ops);
+        let at = (index..=top).rev().find_map(|nested| {
+            let (list, _) = op_list(root, &frames[index..=nested]).ok()?;
+            match list.get(frames[nested].ip.checked_sub(1)?)? {
+                ForthOp::Word(_, at) | ForthOp::Call(_, at) | ForthOp::Recurse(at) => at.clone(),
+                _ => None,
             }
-            ForthOp::IfElse(then_ops, else_ops) => {
-                let flag = stack.pop().ok_or(EvalError::StackUnderflow)?;
-                if flag != 0 {
+        });
+        trace.entries.push(TraceEntry {
+            word: word.map(|xt| vm.entries[xt].name.clone()),
+            at,
+        });
+    }
+    trace
+}
+
+// Unwind to the innermost CATCH: restore the stack depths it saw, and push
+// the error's throw code. Returns false if no CATCH is running.
+fn catch(error: &EvalError, vm: &mut Forth, frames: &mut Vec<Frame>) -> bool {
+    let Some(index) = frames
+        .iter()
+        .rposition(|frame| matches!(frame.list, List::Catch { .. }))
+    else {
+        return false;
+    };
+    if let List::Catch {
+        depth,
+        return_depth,
+        return_base,
+    } = frames[index].list
+    {
+        vm.stack.resize(depth, 0);
+        vm.return_stack.truncate(return_depth);
+        vm.return_base = return_base;
+    }
+    frames.truncate(index);
+    vm.stack.push(error.code());
+    true
+}
+
+fn run_frames(
+    ops: &[ForthOp],
+    ops_body: &mut Option<Rc<[ForthOp]>>,
+    vm: &mut Forth,
+    frames: &mut Vec<Frame>,
+) -> Result<(), EvalError> {
+    loop {
+        let top = frames.len() - 1;
+        let body = top - frames[top].level;
+        let root = frames[body].root.clone();
+        let (list, parent) = op_list(root.as_deref().unwrap_or(ops), &frames[body..])?;
+        // Keep running this list until an op calls, nests or returns
+        while frames.len() == top + 1 {
+            let idx = frames[top].ip;
+            let Some(op) = list.get(idx) else {
+                if end_of_list(parent, vm, frames)? {
+                    return Ok(());
+                }
+                break;
+            };
+            frames[top].ip = idx + 1; // Default: move to the next instruction
+
+            match op {
+                // Simple ops that just execute and move to the next instruction
+                ForthOp::Push(i) => vm.stack.push(*i),
+                ForthOp::Add => number_ops::add(&mut vm.stack, vm.overflow)?,
+                ForthOp::Subtract => number_ops::subtract(&mut vm.stack, vm.overflow)?,
+                ForthOp::Multiply => number_ops::multiply(&mut vm.stack, vm.overflow)?,
+                ForthOp::Divide => number_ops::divide(&mut vm.stack, vm.overflow)?,
+                ForthOp::Mod => number_ops::mod_op(&mut vm.stack)?,
+                ForthOp::OnePlus => number_ops::one_plus(&mut vm.stack, vm.overflow)?,
+                ForthOp::OneMinus => number_ops::one_minus(&mut vm.stack, vm.overflow)?,
+                ForthOp::Negate => number_ops::negate(&mut vm.stack, vm.overflow)?,
+                ForthOp::Abs => number_ops::abs(&mut vm.stack, vm.overflow)?,
+                ForthOp::Min => number_ops::min(&mut vm.stack)?,
+                ForthOp::Max => number_ops::max(&mut vm.stack)?,
+                ForthOp::SlashMod => number_ops::slash_mod(&mut vm.stack, vm.overflow)?,
+                ForthOp::StarSlash => number_ops::star_slash(&mut vm.stack, vm.overflow)?,
+                ForthOp::StarSlashMod => number_ops::star_slash_mod(&mut vm.stack, vm.overflow)?,
+                ForthOp::FmSlashMod => number_ops::fm_slash_mod(&mut vm.stack, vm.overflow)?,
+                ForthOp::SmSlashRem => number_ops::sm_slash_rem(&mut vm.stack, vm.overflow)?,
+                ForthOp::Ne => number_ops::ne(&mut vm.stack)?,
+                ForthOp::Le => number_ops::le(&mut vm.stack)?,
+                ForthOp::Ge => number_ops::ge(&mut vm.stack)?,
+                ForthOp::ZeroEq => number_ops::zero_eq(&mut vm.stack)?,
+                ForthOp::ZeroNe => number_ops::zero_ne(&mut vm.stack)?,
+                ForthOp::ZeroLt => number_ops::zero_lt(&mut vm.stack)?,
+                ForthOp::ZeroGt => number_ops::zero_gt(&mut vm.stack)?,
+                ForthOp::ULt => number_ops::u_lt(&mut vm.stack)?,
+                ForthOp::UGt => number_ops::u_gt(&mut vm.stack)?,
+                ForthOp::Within => number_ops::within(&mut vm.stack)?,
+                ForthOp::And => number_ops::and(&mut vm.stack)?,
+                ForthOp::Or => number_ops::or(&mut vm.stack)?,
+                ForthOp::Xor => number_ops::xor(&mut vm.stack)?,
+                ForthOp::Invert => number_ops::invert(&mut vm.stack)?,
+                ForthOp::LShift => number_ops::lshift(&mut vm.stack)?,
+                ForthOp::RShift => number_ops::rshift(&mut vm.stack)?,
+                ForthOp::ARShift => number_ops::arshift(&mut vm.stack)?,
+                ForthOp::TwoStar => number_ops::two_star(&mut vm.stack, vm.overflow)?,
+                ForthOp::TwoSlash => number_ops::two_slash(&mut vm.stack)?,
+                ForthOp::Eq => number_ops::eq(&mut vm.stack)?,
+                ForthOp::Lt => number_ops::lt(&mut vm.stack)?,
+                ForthOp::Gt => number_ops::gt(&mut vm.stack)?,
+                ForthOp::Dup => stack_ops::dup(&mut vm.stack)?,
+                ForthOp::Drop => stack_ops::drop_(&mut vm.stack)?,
+                ForthOp::Swap => stack_ops::swap(&mut vm.stack)?,
+                ForthOp::Over => stack_ops::over(&mut vm.stack)?,
+                ForthOp::Rot => stack_ops::rot(&mut vm.stack)?,
+                ForthOp::QDup => stack_ops::q_dup(&mut vm.stack)?,
+                ForthOp::TwoDup => stack_ops::two_dup(&mut vm.stack)?,
+                ForthOp::TwoDrop => stack_ops::two_drop(&mut vm.stack)?,
+                ForthOp::TwoSwap => stack_ops::two_swap(&mut vm.stack)?,
+                ForthOp::TwoOver => stack_ops::two_over(&mut vm.stack)?,
+                ForthOp::MinusRot => stack_ops::minus_rot(&mut vm.stack)?,
+                ForthOp::Here => memory_ops::here(&mut vm.stack, &vm.memory)?,
+                ForthOp::Allot => memory_ops::allot(&mut vm.stack, &mut vm.memory)?,
+                ForthOp::Comma => memory_ops::comma(&mut vm.stack, &mut vm.memory)?,
+                ForthOp::CComma => memory_ops::c_comma(&mut vm.stack, &mut vm.memory)?,
+                ForthOp::Fetch => memory_ops::fetch(&mut vm.stack, &vm.memory)?,
+                ForthOp::Store => memory_ops::store(&mut vm.stack, &mut vm.memory)?,
+                ForthOp::TwoFetch => memory_ops::two_fetch(&mut vm.stack, &vm.memory)?,
+                ForthOp::TwoStore => memory_ops::two_store(&mut vm.stack, &mut vm.memory)?,
+                ForthOp::CFetch => memory_ops::c_fetch(&mut vm.stack, &vm.memory)?,
+                ForthOp::CStore => memory_ops::c_store(&mut vm.stack, &mut vm.memory)?,
+                ForthOp::PlusStore => {
+                    memory_ops::plus_store(&mut vm.stack, &mut vm.memory, vm.overflow)?
+                }
+                ForthOp::Cells => memory_ops::cells(&mut vm.stack, vm.overflow)?,
+                ForthOp::CellPlus => memory_ops::cell_plus(&mut vm.stack, vm.overflow)?,
+                ForthOp::Chars => memory_ops::chars(&mut vm.stack)?,
+                ForthOp::Align => memory_ops::align(&mut vm.memory)?,
+                ForthOp::Aligned => memory_ops::aligned(&mut vm.stack)?,
+                ForthOp::Print => {
+                    let base = vm.base();
+                    format_ops::dot(&mut vm.stack, base, &mut io::stdout())?
+                }
+                ForthOp::PrintStack => format_ops::dot_s(&vm.stack, vm.base(), &mut io::stdout())?,
+                ForthOp::PrintString(text) => {
+                    io_ops::write_bytes(&mut io::stdout(), text.as_bytes())?
+                }
+                ForthOp::Type => io_ops::type_string(&mut vm.stack, &vm.memory, &mut io::stdout())?,
+                ForthOp::Emit => io_ops::emit(&mut vm.stack, &mut io::stdout())?,
+                ForthOp::Cr => io_ops::cr(&mut io::stdout())?,
+                ForthOp::Space => io_ops::space(&mut io::stdout())?,
+                ForthOp::Spaces => io_ops::spaces(&mut vm.stack, &mut io::stdout())?,
+                ForthOp::Bl => vm.stack.push(b' ' as i64),
+                ForthOp::Hex => memory_ops::write_cell(&mut vm.memory, BASE_ADDR, 16)?,
+                ForthOp::Decimal => memory_ops::write_cell(&mut vm.memory, BASE_ADDR, 10)?,
+                ForthOp::Binary => memory_ops::write_cell(&mut vm.memory, BASE_ADDR, 2)?,
+                ForthOp::Base => vm.stack.push(BASE_ADDR),
+                ForthOp::UDot => {
+                    let base = vm.base();
+                    format_ops::u_dot(&mut vm.stack, base, &mut io::stdout())?
+                }
+                ForthOp::DotR => {
+                    let base = vm.base();
+                    format_ops::dot_r(&mut vm.stack, base, &mut io::stdout())?
+                }
+                ForthOp::UDotR => {
+                    let base = vm.base();
+                    format_ops::u_dot_r(&mut vm.stack, base, &mut io::stdout())?
+                }
+                ForthOp::LessNumberSign => format_ops::less_number_sign(&mut vm.hold)?,
+                ForthOp::NumberSign => {
+                    let base = vm.base();
+                    format_ops::number_sign(&mut vm.stack, &mut vm.hold, base)?
+                }
+                ForthOp::NumberSignS => {
+                    let base = vm.base();
+                    format_ops::number_sign_s(&mut vm.stack, &mut vm.hold, base)?
+                }
+                ForthOp::Hold => format_ops::hold_char(&mut vm.stack, &mut vm.hold)?,
+                ForthOp::Sign => format_ops::sign(&mut vm.stack, &mut vm.hold)?,
+                ForthOp::NumberSignGreater => {
+                    format_ops::number_sign_greater(&mut vm.stack, &vm.hold, &mut vm.memory)?
+                }
+                ForthOp::Key => io_ops::key(&mut vm.stack, &mut vm.key_input)?,
+                ForthOp::KeyQuestion => io_ops::key_question(&mut vm.stack, &mut vm.key_input)?,
+                ForthOp::Accept => {
+                    io_ops::accept(&mut vm.stack, &mut vm.memory, &mut vm.key_input)?
+                }
+                ForthOp::SLiteral(addr, len) => vm.stack.extend([*addr, *len]),
+                ForthOp::Count => memory_ops::count(&mut vm.stack, &vm.memory)?,
+                // Updated to use Define with immediate flag and store in DictEntry
+                ForthOp::Define(name, body, immediate) => {
+                    // Also updates the latest word for IMMEDIATE
+                    vm.add_entry(DictEntry {
+                        name: name.clone(),
+                        body: match vm.backend {
+                            Backend::Bytecode => bytecode::flatten(body).into(),
+                            Backend::Tree => body.as_slice().into(),
+                        },
+                        immediate: *immediate,
+                        kind: WordKind::Colon,
+                    });
+                }
+                ForthOp::Immediate => {
+                    if let Some(word) = vm.latest_word.clone() {
+                        if let Some(entry) = vm.lookup_mut(&word) {
+                            entry.immediate = true;
+                        }
+                    } else {
+                        return Err(EvalError::NoRecentDefinition);
+                    }
+                }
+                ForthOp::Native(native) => (native.func)(vm)?,
+                ForthOp::Variable(name) => {
+                    let name = parsed_name(name, vm, "VARIABLE")?;
+                    let addr = allot_cells(vm, 1)?;
+                    define_kind(vm, &name, WordKind::Variable(addr));
+                }
+                ForthOp::TwoVariable(name) => {
+                    let name = parsed_name(name, vm, "2VARIABLE")?;
+                    let addr = allot_cells(vm, 2)?;
+                    define_kind(vm, &name, WordKind::Variable(addr));
+                }
+                ForthOp::Constant(name) => {
+                    let name = parsed_name(name, vm, "CONSTANT")?;
+                    let x = vm.stack.pop().ok_or(EvalError::StackUnderflow)?;
+                    define_kind(vm, &name, WordKind::Constant(x));
+                }
+                ForthOp::TwoConstant(name) => {
+                    let name = parsed_name(name, vm, "2CONSTANT")?;
+                    let x2 = vm.stack.pop().ok_or(EvalError::StackUnderflow)?;
+                    let x1 = vm.stack.pop().ok_or(EvalError::StackUnderflow)?;
+                    define_kind(vm, &name, WordKind::TwoConstant(x1, x2));
+                }
+                ForthOp::Value(name) => {
+                    let name = parsed_name(name, vm, "VALUE")?;
+                    let x = vm.stack.pop().ok_or(EvalError::StackUnderflow)?;
+                    let addr = allot_cells(vm, 1)?;
+                    memory_ops::write_cell(&mut vm.memory, addr, x)?;
+                    define_kind(vm, &name, WordKind::Value(addr));
+                }
+                ForthOp::Create(name) => {
+                    // The data field starts at the next cell; ALLOT and , fill it in
+                    let name = parsed_name(name, vm, "CREATE")?;
+                    let addr = allot_cells(vm, 0)?;
+                    define_kind(vm, &name, WordKind::Created(addr));
+                }
+                ForthOp::Does(code) => {
+                    // Give the word being defined its runtime behaviour, and finish
+                    // running the defining word
+                    let name = vm
+                        .latest_word
+                        .clone()
+                        .ok_or(EvalError::NoRecentDefinition)?;
+                    match vm.lookup_mut(&name) {
+                        Some(entry) if matches!(entry.kind, WordKind::Created(_)) => {
+                            entry.body = code.as_slice().into();
+                        }
+                        _ => return Err(EvalError::NotCreated(name)),
+                    }
+                    if return_from_body(vm, frames)? {
+                        return Ok(());
+                    }
+                }
+                ForthOp::To(name) => match vm.lookup(name).map(|entry| entry.kind) {
+                    Some(WordKind::Value(addr)) => {
+                        let x = vm.stack.pop().ok_or(EvalError::StackUnderflow)?;
+                        memory_ops::write_cell(&mut vm.memory, addr, x)?;
+                    }
+                    Some(_) => return Err(EvalError::NotAValue(name.clone())),
+                    None => return Err(EvalError::UnknownWord(name.clone())),
+                },
+                ForthOp::Tick(name) => {
+                    let name = parsed_name(name, vm, "'")?;
+                    let xt = vm.find_xt(&name).ok_or(EvalError::UnknownWord(name))?;
+                    vm.stack.push(xt as i64);
+                }
+                ForthOp::Execute => {
+                    let xt = vm.stack.pop().ok_or(EvalError::StackUnderflow)?;
+                    vm.entry(xt)?;
+                    enter(xt as usize, vm, frames)?;
+                }
+                ForthOp::Catch => {
+                    let xt = vm.stack.pop().ok_or(EvalError::StackUnderflow)?;
+                    vm.entry(xt)?;
+                    let list = List::Catch {
+                        depth: vm.stack.len(),
+                        return_depth: vm.return_stack.len(),
+                        return_base: vm.return_base,
+                    };
+                    push_frame(vm, frames, Frame::new(Some(Rc::from([])), 0, list))?;
+                    enter(xt as usize, vm, frames)?;
+                }
+                ForthOp::Throw => {
+                    let code = vm.stack.pop().ok_or(EvalError::StackUnderflow)?;
+                    if code != 0 {
+                        return Err(EvalError::from_code(code));
+                    }
+                }
+                ForthOp::Abort => return Err(EvalError::Abort),
+                ForthOp::AbortQuote(message) => {
+                    if vm.stack.pop().ok_or(EvalError::StackUnderflow)? != 0 {
+                        return Err(EvalError::AbortQuote(message.clone()));
+                    }
+                }
+                ForthOp::State => vm.stack.push(STATE_ADDR),
+                ForthOp::CompileComma => {
+                    let xt = vm.stack.pop().ok_or(EvalError::StackUnderflow)?;
+                    vm.entry(xt)?;
+                    // The same call the compiler makes for a word it reads, placed
+                    // where COMPILE, or the word running it was read
+                    let at = vm.token_location(vm.input.len());
+                    let Some(compiler) = vm.compiler.as_mut() else {
+                        return Err(EvalError::CompileOnlyWord("COMPILE,".to_string()));
+                    };
+                    compiler.current_body().push(ForthOp::Call(xt as usize, at));
+                }
+                ForthOp::Postpone(word) => {
+                    if vm.compiler.is_none() {
+                        return Err(EvalError::CompileOnlyWord(word.clone()));
+                    }
+                    // Compile the word into the open definition, reading any name
+                    // it needs from the input
+                    let mut input = std::mem::take(&mut vm.input);
+                    let result = with_compiler(vm, |compiler, vm| {
+                        compile_word(word, &mut input, compiler, vm)
+                    });
+                    vm.input = input;
+                    result.map_err(EvalError::Compile)?;
+                }
+                ForthOp::Defer(name) => {
+                    let name = parsed_name(name, vm, "DEFER")?;
+                    define_kind(vm, &name, WordKind::Deferred(None));
+                }
+                ForthOp::Is(name) => {
+                    let xt = vm.stack.pop().ok_or(EvalError::StackUnderflow)?;
+                    vm.entry(xt)?;
+                    deferred_mut(vm, name)?.kind = WordKind::Deferred(Some(xt));
+                }
+                ForthOp::ActionOf(name) => match deferred_mut(vm, name)?.kind {
+                    WordKind::Deferred(Some(xt)) => vm.stack.push(xt),
+                    _ => return Err(EvalError::DeferNotSet(name.clone())),
+                },
+                ForthOp::I => push_loop_index(vm, 0)?,
+                ForthOp::J => push_loop_index(vm, 1)?,
+                ForthOp::K => push_loop_index(vm, 2)?,
+
+                // Ops that call words or jump
+                ForthOp::Word(s, _) => {
+                    if CONTROL_WORDS.contains(&s.to_lowercase().as_str()) {
+                        return Err(EvalError::CompileOnlyWord(s.clone()));
+                    }
+                    let xt = vm
+                        .lookup_xt(&s.to_uppercase())
+                        .ok_or_else(|| EvalError::UnknownWord(s.clone()))?;
+                    enter(xt, vm, frames)?;
+                }
+                ForthOp::Call(xt, _) => enter(*xt, vm, frames)?,
+                ForthOp::IfElse(_, _) => {
+                    let flag = vm.stack.pop().ok_or(EvalError::StackUnderflow)?;
                     // Forth true is non-zero
-                    eval(then_ops, stack, dictionary, loop_control_stack, latest_word)?;
-                } else {
-                    eval(else_ops, stack, dictionary, loop_control_stack, latest_word)?;
+                    nest(vm, frames, if flag != 0 { List::Then } else { List::Else })?;
                 }
-                // next_idx remains idx + 1
-            }
-            ForthOp::Do => {
-                let start = stack.pop().ok_or(EvalError::StackUnderflow)?;
-                let limit = stack.pop().ok_or(EvalError::StackUnderflow)?;
-                if start >= limit {
-                    // Loop doesn't execute, jump past matching LOOP
-                    // Pass variants by value
-                    next_idx = find_matching_end(ops, idx, ForthOp::Do, ForthOp::Loop)?;
-                } else {
-                    // Enter loop: push control info, next instruction is inside loop
-                    loop_control_stack.push((idx + 1, start, limit)); // Store index *after* DO
-                    next_idx = idx + 1;
+                ForthOp::BeginUntil(_) => nest(vm, frames, List::Until)?,
+                ForthOp::BeginWhileRepeat(_, _) => nest(vm, frames, List::While)?,
+                ForthOp::BeginAgain(_) => nest(vm, frames, List::Again)?,
+                ForthOp::Case(clauses, _) => {
+                    let list = if clauses.is_empty() {
+                        List::CaseDefault
+                    } else {
+                        List::CaseValue(0)
+                    };
+                    nest(vm, frames, list)?;
                 }
-            }
-            ForthOp::Loop => {
-                // Peek at the top loop control entry
-                if let Some((loop_start_idx, current_index, limit)) = loop_control_stack.last_mut()
-                {
-                    *current_index += 1; // Increment index
-
-                    if *current_index >= *limit {
-                        // Loop finished: pop control info, continue after LOOP
-                        loop_control_stack.pop();
-                        next_idx = idx + 1;
+                ForthOp::Do | ForthOp::QDo => {
+                    let start = vm.stack.pop().ok_or(EvalError::StackUnderflow)?;
+                    let limit = vm.stack.pop().ok_or(EvalError::StackUnderflow)?;
+                    let end_idx = find_matching_end(list, idx)?;
+                    // DO ... LOOP never runs when start >= limit; ?DO also skips when
+                    // start = limit, which matters for +LOOP
+                    let skip = (list[end_idx - 1] == ForthOp::Loop && start >= limit)
+                        || (*op == ForthOp::QDo && start == limit);
+                    if skip {
+                        // Loop doesn't execute, jump past matching LOOP
+                        frames[top].ip = end_idx;
+                    } else {
+                        // Enter loop: push its parameters, next instruction is inside loop
+                        vm.return_stack.extend([limit, start]);
+                        frames[top].loops.push((idx + 1, end_idx));
+                    }
+                }
+                ForthOp::Loop | ForthOp::PlusLoop => {
+                    let step = if *op == ForthOp::PlusLoop {
+                        vm.stack.pop().ok_or(EvalError::StackUnderflow)?
+                    } else {
+                        1
+                    };
+                    // LOOP without corresponding DO
+                    let Some(&(loop_start_idx, _)) = frames[top].loops.last() else {
+                        return Err(EvalError::LoopStackUnderflow);
+                    };
+                    if step_loop(vm, step, *op == ForthOp::PlusLoop)? {
+                        // Loop finished: continue after LOOP
+                        frames[top].loops.pop();
                     } else {
                         // Loop continues: jump back to instruction after DO
-                        next_idx = *loop_start_idx;
+                        frames[top].ip = loop_start_idx;
                     }
-                } else {
-                    // LOOP without corresponding DO on control stack
-                    return Err(EvalError::LoopStackUnderflow);
                 }
-            }
-        } // end match op
-
-        idx = next_idx; // Update instruction pointer for the next iteration
-    } // end while loop
-    Ok(())
+                // Flat control flow
+                ForthOp::Branch(offset) => frames[top].ip = idx.wrapping_add_signed(*offset),
+                ForthOp::ZeroBranch(offset) => {
+                    if vm.stack.pop().ok_or(EvalError::StackUnderflow)? == 0 {
+                        frames[top].ip = idx.wrapping_add_signed(*offset);
+                    }
+                }
+                ForthOp::DoBranch(offset) | ForthOp::QDoBranch(offset) => {
+                    let start = vm.stack.pop().ok_or(EvalError::StackUnderflow)?;
+                    let limit = vm.stack.pop().ok_or(EvalError::StackUnderflow)?;
+                    let end_idx = idx.wrapping_add_signed(*offset);
+                    // The same rule as for DO and ?DO, with the loop's end known
+                    let skip = (matches!(
+                        list.get(end_idx.wrapping_sub(1)),
+                        Some(ForthOp::LoopBranch(_))
+                    ) && start >= limit)
+                        || (matches!(op, ForthOp::QDoBranch(_)) && start == limit);
+                    if skip {
+                        frames[top].ip = end_idx;
+                    } else {
+                        vm.return_stack.extend([limit, start]);
+                    }
+                }
+                ForthOp::LoopBranch(offset) | ForthOp::PlusLoopBranch(offset) => {
+                    let plus = matches!(op, ForthOp::PlusLoopBranch(_));
+                    let step = if plus {
+                        vm.stack.pop().ok_or(EvalError::StackUnderflow)?
+                    } else {
+                        1
+                    };
+                    if !step_loop(vm, step, plus)? {
+                        frames[top].ip = idx.wrapping_add_signed(*offset);
+                    }
+                }
+                ForthOp::LeaveBranch(offset) => {
+                    drop_loop(vm)?;
+                    frames[top].ip = idx.wrapping_add_signed(*offset);
+                }
+                ForthOp::Leave => leave(vm, frames)?,
+                ForthOp::Exit => {
+                    if return_from_body(vm, frames)? {
+                        return Ok(());
+                    }
+                }
+                // The ops given to eval can recurse too
+                ForthOp::Recurse(_) => {
+                    let word = match frames[body].list {
+                        List::Body { word, .. } => word,
+                        _ => None,
+                    };
+                    let body = match &root {
+                        Some(root) => root.clone(),
+                        None => ops_body.get_or_insert_with(|| ops.into()).clone(),
+                    };
+                    call(body, word, vm, frames)?
+                }
+                ForthOp::Unloop => drop_loop(vm)?,
+                ForthOp::ToR => {
+                    let x = vm.stack.pop().ok_or(EvalError::StackUnderflow)?;
+                    vm.return_stack.push(x);
+                }
+                ForthOp::RFrom => {
+                    let x = r_pop(vm)?;
+                    vm.stack.push(x);
+                }
+                ForthOp::RFetch => {
+                    let x = r_peek(vm, 0)?;
+                    vm.stack.push(x);
+                }
+                ForthOp::TwoToR => {
+                    let x2 = vm.stack.pop().ok_or(EvalError::StackUnderflow)?;
+                    let x1 = vm.stack.pop().ok_or(EvalError::StackUnderflow)?;
+                    vm.return_stack.extend([x1, x2]);
+                }
+                ForthOp::TwoRFrom => {
+                    // Check both cells first so an underflow leaves the frame intact
+                    r_peek(vm, 1)?;
+                    let x2 = r_pop(vm)?;
+                    let x1 = r_pop(vm)?;
+                    vm.stack.extend([x1, x2]);
+                }
+                ForthOp::TwoRFetch => {
+                    let (x1, x2) = (r_peek(vm, 1)?, r_peek(vm, 0)?);
+                    vm.stack.extend([x1, x2]);
+                }
+                ForthOp::RDrop => {
+                    r_pop(vm)?;
+                }
+            } // end match op
+        }
+    } // end loop
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::forth::ForthError;
+    use crate::number_ops::Overflow;
     use crate::parser::ForthOp;
     use crate::parser::parse;
     use crate::token::Token;
     use logos::Logos;
-    use std::collections::HashMap;
-
-    // Add the TestError enum
-    #[derive(Debug, PartialEq)]
-    enum TestError {
-        Eval(EvalError),
-        Parse(ParseError),
-    }
-
-    impl From<EvalError> for TestError {
-        fn from(error: EvalError) -> Self {
-            TestError::Eval(error)
-        }
-    }
 
-    impl From<ParseError> for TestError {
-        fn from(error: ParseError) -> Self {
-            TestError::Parse(error)
-        }
-    }
-
-    // Helper to create a default dictionary and loop stack for tests
-    fn default_eval_state() -> (Vec<i64>, HashMap<String, DictEntry>, Vec<(usize, i64, i64)>) {
-        (Vec::new(), HashMap::new(), Vec::new())
-    }
-
-    // Modify run_forth to handle loop stack and return TestError
-    fn run_forth(code: &str) -> Result<Vec<i64>, TestError> {
+    // Parse and evaluate code on a fresh VM, returning the final stack
+    fn run_forth(code: &str) -> Result<Vec<i64>, ForthError> {
         let tokens: Vec<Token> = Token::lexer(code).filter_map(|r| r.ok()).collect();
-        // Parse tokens, converting ParseError to TestError
-        let ops = parse(tokens)?; // This will use From<ParseError> for TestError
-        let (mut stack, mut dict, mut loop_stack) = default_eval_state();
-        let mut latest_word = None;
-        // Eval, converting EvalError to TestError
-        eval(
-            &ops,
-            &mut stack,
-            &mut dict,
-            &mut loop_stack,
-            &mut latest_word,
-        )?; // This will use From<EvalError> for TestError
-        Ok(stack)
+        // Parse tokens, converting ParseError to ForthError
+        let mut vm = Forth::new();
+        let ops = parse(tokens, &mut vm)?;
+        // Eval, converting EvalError to ForthError
+        eval(&ops, &mut vm)?;
+        Ok(vm.stack)
     }
 
-    // --- IMPORTANT: Update ALL existing tests below to use the new loop_stack ---
-
     #[test]
     fn test_eval_push_add() {
         let ops = vec![ForthOp::Push(10), ForthOp::Push(20), ForthOp::Add];
-        let (mut stack, mut dict, mut loop_stack) = default_eval_state();
-        let mut latest_word = None;
-        let result = eval(
-            &ops,
-            &mut stack,
-            &mut dict,
-            &mut loop_stack,
-            &mut latest_word,
-        );
+        let mut vm = Forth::new();
+        let result = eval(&ops, &mut vm);
         assert!(result.is_ok());
-        assert_eq!(stack, vec![30]);
+        assert_eq!(vm.stack, vec![30]);
     }
 
     #[test]
@@ -290,117 +1243,61 @@ mod tests {
             ForthOp::Push(3),
             ForthOp::Subtract,
         ];
-        let (mut stack, mut dict, mut loop_stack) = default_eval_state();
-        let mut latest_word = None;
-        let result = eval(
-            &ops,
-            &mut stack,
-            &mut dict,
-            &mut loop_stack,
-            &mut latest_word,
-        );
+        let mut vm = Forth::new();
+        let result = eval(&ops, &mut vm);
         assert!(result.is_ok());
-        assert_eq!(stack, vec![22]);
+        assert_eq!(vm.stack, vec![22]);
     }
 
     #[test]
     fn test_eval_print() {
         let ops = vec![ForthOp::Push(42), ForthOp::Print];
-        let (mut stack, mut dict, mut loop_stack) = default_eval_state();
-        let mut latest_word = None;
-        let result = eval(
-            &ops,
-            &mut stack,
-            &mut dict,
-            &mut loop_stack,
-            &mut latest_word,
-        );
+        let mut vm = Forth::new();
+        let result = eval(&ops, &mut vm);
         assert!(result.is_ok());
-        assert!(stack.is_empty());
+        assert!(vm.stack.is_empty());
     }
 
     #[test]
     fn test_eval_print_stack() {
         let ops = vec![ForthOp::Push(1), ForthOp::Push(2), ForthOp::PrintStack];
-        let (mut stack, mut dict, mut loop_stack) = default_eval_state();
-        let mut latest_word = None;
-        let result = eval(
-            &ops,
-            &mut stack,
-            &mut dict,
-            &mut loop_stack,
-            &mut latest_word,
-        );
+        let mut vm = Forth::new();
+        let result = eval(&ops, &mut vm);
         assert!(result.is_ok());
-        assert_eq!(stack, vec![1, 2]);
+        assert_eq!(vm.stack, vec![1, 2]);
     }
 
     #[test]
     fn test_eval_stack_underflow() {
         let ops = vec![ForthOp::Add];
-        let (mut stack, mut dict, mut loop_stack) = default_eval_state();
-        let mut latest_word = None;
-        let result = eval(
-            &ops,
-            &mut stack,
-            &mut dict,
-            &mut loop_stack,
-            &mut latest_word,
-        );
+        let mut vm = Forth::new();
+        let result = eval(&ops, &mut vm);
         assert_eq!(result, Err(EvalError::StackUnderflow));
 
         let ops_sub = vec![ForthOp::Push(5), ForthOp::Subtract];
-        let (mut stack_sub, mut dict_sub, mut loop_stack_sub) = default_eval_state();
-        let mut latest_word_sub = None;
-        let result_sub = eval(
-            &ops_sub,
-            &mut stack_sub,
-            &mut dict_sub,
-            &mut loop_stack_sub,
-            &mut latest_word_sub,
-        );
+        let mut vm_sub = Forth::new();
+        let result_sub = eval(&ops_sub, &mut vm_sub);
         assert_eq!(result_sub, Err(EvalError::StackUnderflow));
     }
 
     #[test]
     fn test_eval_division_by_zero() {
         let ops = vec![ForthOp::Push(10), ForthOp::Push(0), ForthOp::Divide];
-        let (mut stack, mut dict, mut loop_stack) = default_eval_state();
-        let mut latest_word = None;
-        let result = eval(
-            &ops,
-            &mut stack,
-            &mut dict,
-            &mut loop_stack,
-            &mut latest_word,
-        );
+        let mut vm = Forth::new();
+        let result = eval(&ops, &mut vm);
         assert_eq!(result, Err(EvalError::DivisionByZero));
 
         let ops_mod = vec![ForthOp::Push(10), ForthOp::Push(0), ForthOp::Mod];
-        let (mut stack_mod, mut dict_mod, mut loop_stack_mod) = default_eval_state();
-        let mut latest_word_mod = None;
-        let result_mod = eval(
-            &ops_mod,
-            &mut stack_mod,
-            &mut dict_mod,
-            &mut loop_stack_mod,
-            &mut latest_word_mod,
-        );
+        let mut vm_mod = Forth::new();
+        let result_mod = eval(&ops_mod, &mut vm_mod);
         assert_eq!(result_mod, Err(EvalError::DivisionByZero));
     }
 
     #[test]
     fn test_eval_unknown_word() {
-        let ops = vec![ForthOp::Word("foo".to_string())];
-        let (mut stack, mut dict, mut loop_stack) = default_eval_state();
-        let mut latest_word = None;
-        let result = eval(
-            &ops,
-            &mut stack,
-            &mut dict,
-            &mut loop_stack,
-            &mut latest_word,
-        );
+        let ops = vec![ForthOp::Word("foo".to_string(), None)];
+        let mut vm = Forth::new();
+        let result = eval(&ops, &mut vm);
         assert_eq!(result, Err(EvalError::UnknownWord("foo".to_string())));
     }
 
@@ -416,17 +1313,10 @@ mod tests {
             ForthOp::Swap,
             ForthOp::Drop,
         ];
-        let (mut stack, mut dict, mut loop_stack) = default_eval_state();
-        let mut latest_word = None;
-        let result = eval(
-            &ops,
-            &mut stack,
-            &mut dict,
-            &mut loop_stack,
-            &mut latest_word,
-        );
+        let mut vm = Forth::new();
+        let result = eval(&ops, &mut vm);
         assert!(result.is_ok());
-        assert_eq!(stack, vec![2, 3, 1, 1]);
+        assert_eq!(vm.stack, vec![2, 3, 1, 1]);
     }
 
     #[test]
@@ -441,17 +1331,10 @@ mod tests {
             ForthOp::TwoOver,
             ForthOp::TwoDrop,
         ];
-        let (mut stack, mut dict, mut loop_stack) = default_eval_state();
-        let mut latest_word = None;
-        let result = eval(
-            &ops,
-            &mut stack,
-            &mut dict,
-            &mut loop_stack,
-            &mut latest_word,
-        );
+        let mut vm = Forth::new();
+        let result = eval(&ops, &mut vm);
         assert!(result.is_ok());
-        assert_eq!(stack, vec![3, 4, 1, 2, 1, 2]);
+        assert_eq!(vm.stack, vec![3, 4, 1, 2, 1, 2]);
     }
 
     #[test]
@@ -461,21 +1344,14 @@ mod tests {
             vec![ForthOp::Push(2), ForthOp::Multiply],
             false,
         )];
-        let (mut stack, mut dict, mut loop_stack) = default_eval_state();
-        let mut latest_word = None;
-        let result = eval(
-            &ops,
-            &mut stack,
-            &mut dict,
-            &mut loop_stack,
-            &mut latest_word,
-        );
+        let mut vm = Forth::new();
+        let result = eval(&ops, &mut vm);
         assert!(result.is_ok());
-        assert!(stack.is_empty());
-        assert!(dict.contains_key("DOUBLE"));
+        assert!(vm.stack.is_empty());
+        assert!(vm.dictionary.contains_key("DOUBLE"));
         assert_eq!(
-            dict["DOUBLE"].body,
-            vec![ForthOp::Push(2), ForthOp::Multiply]
+            *vm.word("DOUBLE").unwrap().body,
+            [ForthOp::Push(2), ForthOp::Multiply]
         );
     }
 
@@ -488,19 +1364,12 @@ mod tests {
                 false,
             ),
             ForthOp::Push(10),
-            ForthOp::Word("DOUBLE".to_string()),
+            ForthOp::Word("DOUBLE".to_string(), None),
         ];
-        let (mut stack, mut dict, mut loop_stack) = default_eval_state();
-        let mut latest_word = None;
-        let result = eval(
-            &ops,
-            &mut stack,
-            &mut dict,
-            &mut loop_stack,
-            &mut latest_word,
-        );
+        let mut vm = Forth::new();
+        let result = eval(&ops, &mut vm);
         assert!(result.is_ok());
-        assert_eq!(stack, vec![20]);
+        assert_eq!(vm.stack, vec![20]);
     }
 
     #[test]
@@ -508,20 +1377,13 @@ mod tests {
         let ops = vec![
             ForthOp::Define("TEST".to_string(), vec![ForthOp::Push(1)], false),
             ForthOp::Define("TEST".to_string(), vec![ForthOp::Push(2)], false),
-            ForthOp::Word("TEST".to_string()),
+            ForthOp::Word("TEST".to_string(), None),
         ];
-        let (mut stack, mut dict, mut loop_stack) = default_eval_state();
-        let mut latest_word = None;
-        let result = eval(
-            &ops,
-            &mut stack,
-            &mut dict,
-            &mut loop_stack,
-            &mut latest_word,
-        );
+        let mut vm = Forth::new();
+        let result = eval(&ops, &mut vm);
         assert!(result.is_ok());
-        assert_eq!(stack, vec![2]);
-        assert_eq!(dict["TEST"].body, vec![ForthOp::Push(2)]);
+        assert_eq!(vm.stack, vec![2]);
+        assert_eq!(*vm.word("TEST").unwrap().body, [ForthOp::Push(2)]);
     }
 
     #[test]
@@ -533,19 +1395,12 @@ mod tests {
                 false,
             ),
             ForthOp::Push(5),
-            ForthOp::Word("SQUARE".to_string()),
+            ForthOp::Word("SQUARE".to_string(), None),
         ];
-        let (mut stack, mut dict, mut loop_stack) = default_eval_state();
-        let mut latest_word = None;
-        let result = eval(
-            &ops,
-            &mut stack,
-            &mut dict,
-            &mut loop_stack,
-            &mut latest_word,
-        );
+        let mut vm = Forth::new();
+        let result = eval(&ops, &mut vm);
         assert!(result.is_ok());
-        assert_eq!(stack, vec![25]);
+        assert_eq!(vm.stack, vec![25]);
     }
 
     #[test]
@@ -559,25 +1414,18 @@ mod tests {
             ForthOp::Define(
                 "QUADRUPLE".to_string(),
                 vec![
-                    ForthOp::Word("DOUBLE".to_string()),
-                    ForthOp::Word("DOUBLE".to_string()),
+                    ForthOp::Word("DOUBLE".to_string(), None),
+                    ForthOp::Word("DOUBLE".to_string(), None),
                 ],
                 false,
             ),
             ForthOp::Push(3),
-            ForthOp::Word("QUADRUPLE".to_string()),
+            ForthOp::Word("QUADRUPLE".to_string(), None),
         ];
-        let (mut stack, mut dict, mut loop_stack) = default_eval_state();
-        let mut latest_word = None;
-        let result = eval(
-            &ops,
-            &mut stack,
-            &mut dict,
-            &mut loop_stack,
-            &mut latest_word,
-        );
+        let mut vm = Forth::new();
+        let result = eval(&ops, &mut vm);
         assert!(result.is_ok());
-        assert_eq!(stack, vec![12]);
+        assert_eq!(vm.stack, vec![12]);
     }
 
     #[test]
@@ -585,20 +1433,13 @@ mod tests {
         let ops = vec![
             ForthOp::Define(
                 "TEST".to_string(),
-                vec![ForthOp::Word("UNKNOWN".to_string())],
+                vec![ForthOp::Word("UNKNOWN".to_string(), None)],
                 false,
             ),
-            ForthOp::Word("TEST".to_string()),
+            ForthOp::Word("TEST".to_string(), None),
         ];
-        let (mut stack, mut dict, mut loop_stack) = default_eval_state();
-        let mut latest_word = None;
-        let result = eval(
-            &ops,
-            &mut stack,
-            &mut dict,
-            &mut loop_stack,
-            &mut latest_word,
-        );
+        let mut vm = Forth::new();
+        let result = eval(&ops, &mut vm);
         assert_eq!(result, Err(EvalError::UnknownWord("UNKNOWN".to_string())));
     }
 
@@ -639,7 +1480,6 @@ mod tests {
         assert_eq!(result.unwrap(), vec![1, 2, 3]); // 0+1, 1+1, 2+1
     }
 
-    /* // Nested loop test - requires J implementation
     #[test]
     fn test_eval_nested_loop() {
         // : TEST 2 0 DO 3 0 DO I J + LOOP LOOP ; TEST
@@ -648,37 +1488,1172 @@ mod tests {
         assert!(result.is_ok(), "Eval failed: {:?}", result.err());
         assert_eq!(result.unwrap(), vec![0, 1, 2, 1, 2, 3]);
     }
-    */
 
     #[test]
-    fn test_eval_error_loop_stack_underflow_loop() {
-        // LOOP without DO - This should be a ParseError now, but test eval robustness
-        let ops = vec![ForthOp::Loop];
-        let (mut stack, mut dict, mut loop_stack) = default_eval_state();
-        let mut latest_word = None;
-        let result = eval(
-            &ops,
-            &mut stack,
-            &mut dict,
-            &mut loop_stack,
-            &mut latest_word,
-        );
-        assert_eq!(result, Err(EvalError::LoopStackUnderflow));
+    fn test_eval_triple_nested_loop_k() {
+        let code = ": TEST 2 0 DO 1 0 DO 2 0 DO K 100 * J 10 * + I + LOOP LOOP LOOP ; TEST";
+        assert_eq!(run_forth(code).unwrap(), vec![0, 1, 100, 101]);
     }
 
     #[test]
-    fn test_eval_error_loop_stack_underflow_i() {
-        // I without DO
-        let ops = vec![ForthOp::I];
-        let (mut stack, mut dict, mut loop_stack) = default_eval_state();
-        let mut latest_word = None;
-        let result = eval(
-            &ops,
-            &mut stack,
-            &mut dict,
-            &mut loop_stack,
-            &mut latest_word,
+    fn test_eval_plus_loop() {
+        assert_eq!(
+            run_forth(": TEST 10 0 DO I 3 +LOOP ; TEST").unwrap(),
+            vec![0, 3, 6, 9]
         );
+        // Stops exactly at the limit
+        assert_eq!(
+            run_forth(": TEST 6 0 DO I 2 +LOOP ; TEST").unwrap(),
+            vec![0, 2, 4]
+        );
+        // Negative steps count down and include the limit
+        assert_eq!(
+            run_forth(": TEST 0 4 DO I -1 +LOOP ; TEST").unwrap(),
+            vec![4, 3, 2, 1, 0]
+        );
+        assert_eq!(
+            run_forth(": TEST -5 5 DO I -4 +LOOP ; TEST").unwrap(),
+            vec![5, 1, -3]
+        );
+        // DO ... +LOOP always runs at least once
+        assert_eq!(
+            run_forth(": TEST 5 5 DO I -1 +LOOP ; TEST").unwrap(),
+            vec![5]
+        );
+    }
+
+    #[test]
+    fn test_eval_plus_loop_boundary() {
+        assert!(plus_loop_done(9, 10, 1));
+        assert!(!plus_loop_done(8, 10, 1));
+        assert!(plus_loop_done(10, 10, -1));
+        assert!(!plus_loop_done(11, 10, -1));
+        // Wrapping around from i64::MAX crosses no boundary unless the limit lies there
+        assert!(!plus_loop_done(i64::MAX, 0, 1));
+        assert!(plus_loop_done(i64::MAX, i64::MIN, 1));
+    }
+
+    #[test]
+    fn test_eval_qdo() {
+        // ?DO skips the loop when start = limit
+        assert!(
+            run_forth(": TEST 3 3 ?DO I LOOP ; TEST")
+                .unwrap()
+                .is_empty()
+        );
+        assert!(
+            run_forth(": TEST 3 3 ?DO I -1 +LOOP ; TEST")
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(
+            run_forth(": TEST 3 0 ?DO I LOOP ; TEST").unwrap(),
+            vec![0, 1, 2]
+        );
+    }
+
+    #[test]
+    fn test_eval_leave() {
+        // LEAVE directly in the loop body
+        assert_eq!(
+            run_forth(": TEST 10 0 DO I LEAVE 99 LOOP 7 ; TEST").unwrap(),
+            vec![0, 7]
+        );
+        // LEAVE from inside IF only exits the innermost loop
+        let code = ": TEST 3 0 DO 10 0 DO I J = IF LEAVE THEN I LOOP LOOP ; TEST";
+        assert_eq!(run_forth(code).unwrap(), vec![0, 0, 1]);
+        // LEAVE from inside BEGIN ... UNTIL
+        let code = ": TEST 5 0 DO BEGIN I 2 = IF LEAVE THEN I -1 UNTIL LOOP ; TEST";
+        assert_eq!(run_forth(code).unwrap(), vec![0, 1]);
+        let mut vm = Forth::new();
+        vm.interpret(code).unwrap();
+        assert!(vm.return_stack.is_empty());
+    }
+
+    #[test]
+    fn test_eval_unloop() {
+        let mut vm = Forth::new();
+        vm.return_stack.extend([3, 1]); // limit, index
+        assert!(eval(&[ForthOp::Unloop], &mut vm).is_ok());
+        assert!(vm.return_stack.is_empty());
+        assert_eq!(
+            eval(&[ForthOp::Unloop], &mut vm),
+            Err(EvalError::LoopStackUnderflow)
+        );
+    }
+
+    #[test]
+    fn test_eval_error_outer_loop_index_underflow() {
+        let mut vm = Forth::new();
+        vm.return_stack.extend([10, 5]); // limit, index
+        assert_eq!(
+            eval(&[ForthOp::J], &mut vm),
+            Err(EvalError::LoopStackUnderflow)
+        );
+        assert_eq!(eval(&[ForthOp::I], &mut vm), Ok(()));
+        assert_eq!(vm.stack, vec![5]);
+    }
+
+    #[test]
+    fn test_eval_begin_until() {
+        // Count down from 3, leaving each value on the stack
+        let code = ": TEST 3 begin dup 1 - dup 0 = until ; TEST";
+        assert_eq!(run_forth(code).unwrap(), vec![3, 2, 1, 0]);
+        // The body runs at least once
+        assert_eq!(
+            run_forth(": TEST begin 7 -1 until ; TEST").unwrap(),
+            vec![7]
+        );
+    }
+
+    #[test]
+    fn test_eval_begin_while_repeat() {
+        // Sum 1..n for n = 4
+        let code = ": SUM-TO 0 swap begin dup 0 > while dup rot + swap 1 - repeat drop ; 4 SUM-TO";
+        assert_eq!(run_forth(code).unwrap(), vec![10]);
+        // The body is skipped when the condition is false on entry
+        let code = ": TEST begin 0 while 99 repeat ; TEST";
+        assert!(run_forth(code).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_eval_begin_with_do_loop() {
+        let code = ": TEST 0 begin 3 0 do 1 + loop dup 9 = until ; TEST";
+        assert_eq!(run_forth(code).unwrap(), vec![9]);
+    }
+
+    #[test]
+    fn test_eval_begin_until_stack_underflow() {
+        let ops = vec![ForthOp::BeginUntil(vec![])];
+        let mut vm = Forth::new();
+        assert_eq!(eval(&ops, &mut vm), Err(EvalError::StackUnderflow));
+    }
+
+    #[test]
+    fn test_eval_case() {
+        let mut vm = Forth::new();
+        vm.interpret(
+            ": DIGIT ( n -- m ) case 1 of 10 endof 2 of 20 endof 1 1 + 1 + of 30 endof 99 swap endcase ;",
+        )
+        .unwrap();
+        vm.interpret("1 DIGIT 2 DIGIT 3 DIGIT 7 DIGIT").unwrap();
+        // The default clause sees the selector, which ENDCASE then drops
+        assert_eq!(vm.stack, vec![10, 20, 30, 99]);
+    }
+
+    #[test]
+    fn test_eval_case_in_loop() {
+        let code = ": TEST 4 0 do i case 0 of 100 endof 2 of 200 endof endcase loop ; TEST";
+        assert_eq!(run_forth(code).unwrap(), vec![100, 200]);
+        // EXIT and LEAVE from inside a clause
+        let code =
+            ": FIRST-TWO 10 0 do i case 2 of i unloop exit endof endcase loop -1 ; FIRST-TWO";
+        assert_eq!(run_forth(code).unwrap(), vec![2]);
+        let code = ": TEST 10 0 do i case 3 of leave endof endcase i loop ; TEST";
+        assert_eq!(run_forth(code).unwrap(), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_eval_case_stack_underflow() {
+        let ops = vec![ForthOp::Case(
+            vec![(vec![ForthOp::Push(1)], vec![])],
+            vec![],
+        )];
+        let mut vm = Forth::new();
+        assert_eq!(eval(&ops, &mut vm), Err(EvalError::StackUnderflow));
+        // The default clause consumed the selector
+        let ops = vec![ForthOp::Push(5), ForthOp::Case(vec![], vec![ForthOp::Drop])];
+        assert_eq!(eval(&ops, &mut vm), Err(EvalError::StackUnderflow));
+    }
+
+    #[test]
+    fn test_eval_memory() {
+        let mut vm = Forth::new();
+        // The data space starts after the system variables
+        let base = vm.memory.len() as i64;
+        // A three-cell array filled with , and read back by index
+        vm.interpret("here 10 , 20 , 30 ,").unwrap();
+        vm.interpret(": TH ( addr n -- addr' ) cells + ;").unwrap();
+        vm.interpret("dup 2 TH @ swap dup 1 TH 5 swap +! 1 TH @")
+            .unwrap();
+        assert_eq!(vm.stack, vec![30, 25]);
+        assert_eq!(vm.memory.len() as i64, base + 24);
+
+        // Bytes after the cells, then ALIGN pads to the next cell
+        vm.stack.clear();
+        vm.interpret("here 65 c, 2 allot 66 over 1 chars + c! dup c@ swap 1 + c@")
+            .unwrap();
+        assert_eq!(vm.stack, vec![65, 66]);
+        vm.interpret("align here 5 aligned 0 cell+").unwrap();
+        assert_eq!(vm.stack, vec![65, 66, base + 32, 8, 8]);
+    }
+
+    #[test]
+    fn test_eval_memory_invalid_address() {
+        let mut vm = Forth::new();
+        let base = vm.memory.len() as i64;
+        vm.interpret("2 cells allot").unwrap();
+        assert_eq!(
+            vm.interpret("here 1 cells - cell+ @"),
+            Err(ForthError::Eval(EvalError::InvalidAddress(base + 16)))
+        );
+        assert_eq!(
+            vm.interpret("42 -1 !"),
+            Err(ForthError::Eval(EvalError::InvalidAddress(-1)))
+        );
+        assert_eq!(
+            vm.interpret(&format!("{} allot", -base - 100)),
+            Err(ForthError::Eval(EvalError::InvalidAddress(-84)))
+        );
+        // Failed accesses leave the data space alone
+        assert_eq!(vm.memory[base as usize..], vec![0; 16]);
+    }
+
+    #[test]
+    fn test_eval_variable() {
+        let mut vm = Forth::new();
+        let base = vm.memory.len() as i64;
+        // Variables start at the next cell boundary and are zeroed
+        vm.interpret("1 c, variable X 2variable POINT").unwrap();
+        vm.interpret("X POINT X @").unwrap();
+        assert_eq!(vm.stack, vec![base + 8, base + 16, 0]);
+        assert_eq!(vm.word("X").unwrap().kind, WordKind::Variable(base + 8));
+        vm.stack.clear();
+        vm.interpret("5 X ! 3 POINT ! 4 POINT cell+ ! : BUMP 1 X +! ; BUMP BUMP")
+            .unwrap();
+        vm.interpret("X @ POINT @ POINT cell+ @").unwrap();
+        assert_eq!(vm.stack, vec![7, 3, 4]);
+        // 2! and 2@ move both cells of a 2VARIABLE, the top one first
+        vm.stack.clear();
+        vm.interpret("10 20 POINT 2! POINT 2@ POINT @").unwrap();
+        assert_eq!(vm.stack, vec![10, 20, 20]);
+    }
+
+    #[test]
+    fn test_eval_constant() {
+        let mut vm = Forth::new();
+        vm.interpret("42 constant ANSWER 1 2 2constant PAIR")
+            .unwrap();
+        vm.interpret(": TEST ANSWER PAIR + + ; TEST answer")
+            .unwrap();
+        assert_eq!(vm.stack, vec![45, 42]);
+        assert_eq!(vm.word("PAIR").unwrap().kind, WordKind::TwoConstant(1, 2));
+        vm.stack.clear();
+        assert_eq!(
+            vm.interpret("constant EMPTY"),
+            Err(ForthError::Eval(EvalError::StackUnderflow))
+        );
+    }
+
+    #[test]
+    fn test_eval_value_and_to() {
+        let mut vm = Forth::new();
+        vm.interpret("10 value LIMIT LIMIT 20 to LIMIT LIMIT")
+            .unwrap();
+        assert_eq!(vm.stack, vec![10, 20]);
+        // TO inside a definition updates the value when the word runs
+        vm.interpret(": DOUBLE-LIMIT LIMIT 2 * to limit ; DOUBLE-LIMIT DOUBLE-LIMIT LIMIT")
+            .unwrap();
+        assert_eq!(vm.stack, vec![10, 20, 80]);
+        // The value lives in data space
+        let WordKind::Value(addr) = vm.word("LIMIT").unwrap().kind else {
+            panic!("LIMIT is not a value");
+        };
+        vm.interpret(&format!("{} @", addr)).unwrap();
+        assert_eq!(vm.stack, vec![10, 20, 80, 80]);
+    }
+
+    #[test]
+    fn test_eval_to_errors() {
+        let mut vm = Forth::new();
+        vm.interpret("variable X : SQUARE dup * ;").unwrap();
+        assert_eq!(
+            vm.interpret("1 to X"),
+            Err(ForthError::Eval(EvalError::NotAValue("X".to_string())))
+        );
+        assert_eq!(
+            vm.interpret("1 to SQUARE"),
+            Err(ForthError::Eval(EvalError::NotAValue("SQUARE".to_string())))
+        );
+        assert_eq!(
+            vm.interpret("1 to NOPE"),
+            Err(ForthError::Eval(EvalError::UnknownWord("NOPE".to_string())))
+        );
+        vm.stack.clear();
+        assert_eq!(
+            vm.interpret("0 value V to V"),
+            Err(ForthError::Eval(EvalError::StackUnderflow))
+        );
+    }
+
+    #[test]
+    fn test_eval_create() {
+        let mut vm = Forth::new();
+        let base = vm.memory.len() as i64;
+        vm.interpret("1 c, create TABLE 10 , 20 , 30 , TABLE TABLE 2 cells + @")
+            .unwrap();
+        assert_eq!(vm.stack, vec![base + 8, 30]);
+        assert_eq!(vm.word("TABLE").unwrap().kind, WordKind::Created(base + 8));
+    }
+
+    #[test]
+    fn test_eval_create_does() {
+        let mut vm = Forth::new();
+        vm.interpret(": CONST create , does> @ ;").unwrap();
+        vm.interpret("42 CONST ANSWER 7 CONST WEEK").unwrap();
+        vm.interpret("ANSWER WEEK : TEST ANSWER 1 + ; TEST")
+            .unwrap();
+        assert_eq!(vm.stack, vec![42, 7, 43]);
+
+        vm.stack.clear();
+        vm.interpret(": ARRAY ( n -- ) create cells allot does> ( i -- addr ) swap cells + ;")
+            .unwrap();
+        vm.interpret("4 ARRAY A 5 0 A ! 9 3 A ! 0 A @ 3 A @ 1 A @")
+            .unwrap();
+        assert_eq!(vm.stack, vec![5, 9, 0]);
+    }
+
+    #[test]
+    fn test_eval_does_ends_defining_word() {
+        let mut vm = Forth::new();
+        // The code after DOES> only runs when the new word does
+        vm.interpret(": COUNTER create 0 , does> 1 over +! @ ; COUNTER HITS 99")
+            .unwrap();
+        assert_eq!(vm.stack, vec![99]);
+        vm.interpret("HITS HITS HITS").unwrap();
+        assert_eq!(vm.stack, vec![99, 1, 2, 3]);
+    }
+
+    #[test]
+    fn test_eval_defining_words_read_name_at_runtime() {
+        let mut vm = Forth::new();
+        vm.interpret(": MKVAR variable ; : MKCONST constant ; MKVAR V 3 MKCONST THREE")
+            .unwrap();
+        vm.interpret("THREE V ! V @ THREE").unwrap();
+        assert_eq!(vm.stack, vec![3, 3]);
+        assert_eq!(
+            vm.interpret("MKVAR").unwrap_err().eval_error(),
+            Some(&EvalError::MissingName("VARIABLE".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_eval_does_without_create() {
+        let mut vm = Forth::new();
+        vm.interpret(": BAD does> 1 ;").unwrap();
+        assert_eq!(
+            vm.interpret("BAD").unwrap_err().eval_error(),
+            Some(&EvalError::NotCreated("BAD".to_string()))
+        );
+        // An ordinary word cannot be given a DOES> body either
+        vm.interpret(": SET-DOES does> ; 5 constant FIVE").unwrap();
+        assert_eq!(
+            vm.interpret("SET-DOES").unwrap_err().eval_error(),
+            Some(&EvalError::NotCreated("FIVE".to_string()))
+        );
+        let mut vm = Forth::new();
+        assert_eq!(
+            eval(&[ForthOp::Does(vec![])], &mut vm),
+            Err(EvalError::NoRecentDefinition)
+        );
+    }
+
+    #[test]
+    fn test_eval_return_stack() {
+        assert_eq!(
+            run_forth(": TEST 1 2 >r 10 + r> ; TEST").unwrap(),
+            vec![11, 2]
+        );
+        assert_eq!(
+            run_forth(": TEST 5 >r r@ r@ * r> + ; TEST").unwrap(),
+            vec![30]
+        );
+        assert_eq!(
+            run_forth(": TEST 1 2 2>r 2r@ 2r> ; TEST").unwrap(),
+            vec![1, 2, 1, 2]
+        );
+        assert_eq!(
+            run_forth(": TEST 1 >r 2 >r rdrop r> ; TEST").unwrap(),
+            vec![1]
+        );
+    }
+
+    #[test]
+    fn test_eval_return_stack_holds_loop_parameters() {
+        // I is the top of the return stack inside a loop
+        let code = ": TEST 3 0 do r@ i = loop ; TEST";
+        assert_eq!(run_forth(code).unwrap(), vec![-1, -1, -1]);
+        // Data moved to the return stack sits above the loop parameters, so
+        // I reads it instead of the index until it is moved back
+        let code = ": TEST 2 0 do i 10 * >r i r> + loop ; TEST";
+        assert_eq!(run_forth(code).unwrap(), vec![0, 20]);
+        // A called word has its own frame, so it cannot see the caller's loop
+        let mut vm = Forth::new();
+        vm.interpret(": SHOW i ;").unwrap();
+        assert_eq!(
+            vm.interpret(": TEST 3 0 do SHOW loop ; TEST")
+                .unwrap_err()
+                .eval_error(),
+            Some(&EvalError::LoopStackUnderflow)
+        );
+        assert!(vm.return_stack.is_empty());
+        // An index set to the largest cell steps past it like the increment does
+        let code = ": TEST 10 0 do r> r> 2drop -9223372036854775808 >r 9223372036854775807 >r i loop ; TEST";
+        assert_eq!(run_forth(code).unwrap(), vec![i64::MAX]);
+    }
+
+    #[test]
+    fn test_eval_loop_index_wraps_when_checked() {
+        // Checked mode is for arithmetic: LOOP and +LOOP end by crossing the
+        // limit, so their index still steps over the largest cell
+        let mut vm = Forth::new();
+        vm.set_overflow(Overflow::Check);
+        let code =
+            ": T 10 0 do r> r> 2drop -9223372036854775808 >r 9223372036854775807 >r i loop ; T";
+        vm.interpret(code).unwrap();
+        let code = ": T2 -9223372036854775806 9223372036854775806 do i 2 +loop ; T2";
+        vm.interpret(code).unwrap();
+        assert_eq!(vm.stack, vec![i64::MAX, i64::MAX - 1, i64::MIN]);
+    }
+
+    #[test]
+    fn test_eval_return_stack_errors() {
+        let mut vm = Forth::new();
+        assert_eq!(
+            vm.interpret(": BAD 1 >r ; BAD").unwrap_err().eval_error(),
+            Some(&EvalError::ReturnStackImbalance)
+        );
+        assert_eq!(
+            vm.interpret(": BAD2 r> ; BAD2").unwrap_err().eval_error(),
+            Some(&EvalError::ReturnStackUnderflow)
+        );
+        // A word cannot reach into its caller's frame
+        vm.stack.clear();
+        assert_eq!(
+            vm.interpret(": INNER r@ ; : OUTER 1 >r INNER r> ; OUTER")
+                .unwrap_err()
+                .eval_error(),
+            Some(&EvalError::ReturnStackUnderflow)
+        );
+        // Errors unwind the return stack
+        assert!(vm.return_stack.is_empty());
+        assert_eq!(vm.return_base, 0);
+
+        let mut vm = Forth::new();
+        vm.return_stack.push(1);
+        assert_eq!(
+            eval(&[ForthOp::TwoRFrom], &mut vm),
+            Err(EvalError::ReturnStackUnderflow)
+        );
+        assert_eq!(vm.return_stack, vec![1]);
+        assert_eq!(
+            eval(&[ForthOp::TwoRFetch], &mut vm),
+            Err(EvalError::ReturnStackUnderflow)
+        );
+        assert_eq!(
+            eval(&[ForthOp::ToR], &mut vm),
+            Err(EvalError::StackUnderflow)
+        );
+    }
+
+    #[test]
+    fn test_eval_tick_execute() {
+        let mut vm = Forth::new();
+        vm.interpret(": SQUARE dup * ; 5 ' square execute").unwrap();
+        assert_eq!(vm.stack, vec![25]);
+        // ['] compiles the token; ' in a definition reads the name when it runs
+        vm.interpret(": APPLY ( n xt -- ) execute ; : TEST 3 ['] SQUARE APPLY ; TEST")
+            .unwrap();
+        vm.interpret(": TICK-IT ' ; 4 TICK-IT SQUARE execute")
+            .unwrap();
+        assert_eq!(vm.stack, vec![25, 9, 16]);
+    }
+
+    #[test]
+    fn test_eval_execution_tokens_are_stable() {
+        let mut vm = Forth::new();
+        vm.interpret(": GREETING 1 ; ' GREETING").unwrap();
+        vm.interpret(": GREETING 2 ; ' GREETING").unwrap();
+        let (old, new) = (vm.stack[0], vm.stack[1]);
+        assert_ne!(old, new);
+        vm.stack.clear();
+        vm.interpret(&format!("{} execute {} execute GREETING", old, new))
+            .unwrap();
+        assert_eq!(vm.stack, vec![1, 2, 2]);
+    }
+
+    #[test]
+    fn test_eval_execute_built_in_words() {
+        let mut vm = Forth::new();
+        // A dispatch table of built-in operators
+        vm.interpret("create OPS ' + , ' - , ' * ,").unwrap();
+        vm.interpret(": OP ( a b i -- c ) cells OPS + @ execute ;")
+            .unwrap();
+        vm.interpret("6 3 0 OP 6 3 1 OP 6 3 2 OP").unwrap();
+        assert_eq!(vm.stack, vec![9, 3, 18]);
+        // The same built-in always has the same token
+        vm.interpret("' dup ' DUP =").unwrap();
+        assert_eq!(vm.stack.pop(), Some(-1));
+    }
+
+    #[test]
+    fn test_eval_tick_built_in_leaves_dictionary_alone() {
+        let mut vm = Forth::new();
+        vm.interpret("' DUP DROP : T [ ' SWAP DROP ] ['] DUP DROP DUP ;")
+            .unwrap();
+        assert_eq!(vm.dictionary.len(), 1);
+        assert!(vm.word("DUP").is_none());
+        // DUP still compiles to the primitive, not a call to its token's entry
+        let body = &vm.word("T").unwrap().body;
+        assert_eq!(body.last(), Some(&ForthOp::Dup));
+    }
+
+    #[test]
+    fn test_eval_execute_errors() {
+        let mut vm = Forth::new();
+        assert_eq!(
+            vm.interpret("' NOPE"),
+            Err(ForthError::Eval(EvalError::UnknownWord("NOPE".to_string())))
+        );
+        for xt in [-1, 999] {
+            assert_eq!(
+                vm.interpret(&format!("{} execute", xt)),
+                Err(ForthError::Eval(EvalError::InvalidExecutionToken(xt)))
+            );
+        }
+        assert_eq!(
+            vm.interpret("execute"),
+            Err(ForthError::Eval(EvalError::StackUnderflow))
+        );
+    }
+
+    #[test]
+    fn test_eval_defer_is() {
+        let mut vm = Forth::new();
+        vm.interpret("defer GREET : HELLO 1 ; : BYE 2 ; : TEST GREET GREET ;")
+            .unwrap();
+        vm.interpret("' HELLO is GREET TEST").unwrap();
+        // IS inside a definition, and calls through GREET pick up the change
+        vm.interpret(": USE-BYE ['] BYE is greet ; USE-BYE TEST")
+            .unwrap();
+        assert_eq!(vm.stack, vec![1, 1, 2, 2]);
+        vm.stack.clear();
+        vm.interpret("action-of GREET ' BYE = : GET action-of GREET ; GET ' BYE =")
+            .unwrap();
+        assert_eq!(vm.stack, vec![-1, -1]);
+    }
+
+    #[test]
+    fn test_eval_defer_errors() {
+        let mut vm = Forth::new();
+        vm.interpret("defer LATER : NOW 1 ;").unwrap();
+        assert_eq!(
+            vm.interpret("LATER"),
+            Err(ForthError::Eval(EvalError::DeferNotSet(
+                "LATER".to_string()
+            )))
+        );
+        assert_eq!(
+            vm.interpret("action-of LATER"),
+            Err(ForthError::Eval(EvalError::DeferNotSet(
+                "LATER".to_string()
+            )))
+        );
+        assert_eq!(
+            vm.interpret("' NOW is NOW"),
+            Err(ForthError::Eval(EvalError::NotDeferred("NOW".to_string())))
+        );
+        vm.stack.clear();
+        assert_eq!(
+            vm.interpret("42 is LATER"),
+            Err(ForthError::Eval(EvalError::InvalidExecutionToken(42)))
+        );
+        assert_eq!(
+            vm.interpret("' NOW is NEVER"),
+            Err(ForthError::Eval(EvalError::UnknownWord(
+                "NEVER".to_string()
+            )))
+        );
+    }
+
+    #[test]
+    fn test_eval_state() {
+        let mut vm = Forth::new();
+        vm.interpret(": COMPILING? state @ ; immediate").unwrap();
+        vm.interpret("COMPILING? : TEST COMPILING? literal ; TEST")
+            .unwrap();
+        assert_eq!(vm.stack, vec![0, -1]);
+        assert_eq!(*vm.word("TEST").unwrap().body, [ForthOp::Push(-1)]);
+        // Back to interpreting once the definition is done, even if it failed
+        assert!(vm.interpret(": BROKEN then ;").is_err());
+        vm.interpret("state @").unwrap();
+        assert_eq!(vm.stack, vec![0, -1, 0]);
+    }
+
+    #[test]
+    fn test_eval_postpone_control_structure() {
+        let mut vm = Forth::new();
+        vm.interpret(": UNLESS 0 postpone literal postpone = postpone if ; immediate")
+            .unwrap();
+        vm.interpret(": ENDIF postpone then ; immediate").unwrap();
+        vm.interpret(": TEST unless 1 else 2 endif ; 0 TEST 5 TEST")
+            .unwrap();
+        assert_eq!(vm.stack, vec![1, 2]);
+    }
+
+    #[test]
+    fn test_eval_postpone_words() {
+        let mut vm = Forth::new();
+        // A normal word is compiled into the definition being built when the
+        // immediate word runs
+        vm.interpret(": SQ dup * ; : INLINE-SQ postpone SQ ; immediate")
+            .unwrap();
+        // An immediate word runs when the word containing it runs
+        vm.interpret(": ANSWER 42 ; immediate : ALWAYS postpone ANSWER ;")
+            .unwrap();
+        vm.interpret(": TEST INLINE-SQ 1 + ; 3 TEST ALWAYS")
+            .unwrap();
+        assert_eq!(vm.stack, vec![10, 42]);
+        // A postponed parsing word reads its name from the source being compiled
+        vm.interpret("5 value LIMIT : SET postpone to ; immediate")
+            .unwrap();
+        vm.interpret(": RESET 0 SET LIMIT ; RESET LIMIT").unwrap();
+        assert_eq!(vm.stack, vec![10, 42, 0]);
+    }
+
+    #[test]
+    fn test_eval_compile_comma() {
+        let mut vm = Forth::new();
+        vm.interpret(": COMPILE-DUP ['] dup compile, ; immediate")
+            .unwrap();
+        vm.interpret(": SQ COMPILE-DUP * ; : CUBE dup [ ' SQ compile, ] * ;")
+            .unwrap();
+        vm.interpret("3 SQ 2 CUBE").unwrap();
+        assert_eq!(vm.stack, vec![9, 8]);
+        // It compiles a direct call, which backtraces name
+        let sq = vm.lookup_xt("SQ").unwrap();
+        assert!(matches!(vm.word("CUBE").unwrap().body[1], ForthOp::Call(xt, _) if xt == sq));
+        vm.stack.clear();
+        assert_eq!(
+            vm.interpret(": BAD [ ' SQ compile, ] ; BAD")
+                .unwrap_err()
+                .to_string(),
+            "Error: Stack underflow in DUP (called from SQ at <input>:2:6, called from BAD at <input>:4:14, called at <input>:4:27)"
+        );
+    }
+
+    #[test]
+    fn test_eval_compiling_words_outside_definition() {
+        let mut vm = Forth::new();
+        vm.interpret(": ENDIF postpone then ; immediate : SQ dup * ;")
+            .unwrap();
+        assert_eq!(
+            vm.interpret("' SQ compile,"),
+            Err(ForthError::Eval(EvalError::CompileOnlyWord(
+                "COMPILE,".to_string()
+            )))
+        );
+        assert_eq!(
+            vm.interpret("ENDIF").unwrap_err().eval_error(),
+            Some(&EvalError::CompileOnlyWord("THEN".to_string()))
+        );
+        // A postponed control word still has to match an open structure
+        assert_eq!(
+            vm.interpret(": TEST ENDIF ;"),
+            Err(ForthError::Parse(ParseError::ImmediateWordError(
+                "ENDIF: Compile error: MismatchedConditional".to_string()
+            )))
+        );
+        assert_eq!(
+            vm.interpret(": TEST [ 42 compile, ] ;"),
+            Err(ForthError::Parse(ParseError::ImmediateWordError(
+                "CompileComma: Invalid execution token: 42".to_string()
+            )))
+        );
+    }
+
+    #[test]
+    fn test_eval_strings() {
+        let mut vm = Forth::new();
+        vm.interpret(r#": NAME s" rforth" ; NAME"#).unwrap();
+        let addr = vm.stack[0];
+        assert_eq!(vm.stack, vec![addr, 6]);
+        // Each run gives the same text; it is not copied again
+        vm.interpret("NAME 2drop here").unwrap();
+        assert_eq!(vm.stack, vec![addr, 6, addr + 6]);
+        vm.stack.clear();
+        vm.interpret(r#"c" counted" count s\" a\tb""#).unwrap();
+        assert_eq!(vm.stack[1], 7);
+        assert_eq!(vm.stack[3], 3);
+        assert_eq!(
+            memory_ops::bytes(&vm.memory, vm.stack[2], 3),
+            Ok(&b"a\tb"[..])
+        );
+        vm.interpret("type type").unwrap();
+        assert!(vm.stack.is_empty());
+        assert_eq!(
+            vm.interpret("-1 5 type"),
+            Err(ForthError::Eval(EvalError::InvalidAddress(-1)))
+        );
+        assert_eq!(
+            vm.interpret("type"),
+            Err(ForthError::Eval(EvalError::StackUnderflow))
+        );
+    }
+
+    #[test]
+    fn test_eval_interpreted_strings_are_transient() {
+        let mut vm = Forth::new();
+        vm.interpret("here").unwrap();
+        let here = vm.stack[0];
+        vm.interpret(r#"s" first" c" second" count s" third" here"#)
+            .unwrap();
+        assert_eq!(vm.stack[6..], [5, here]);
+        // Two buffers take turns, so the third string reuses the first's
+        assert_eq!(vm.stack[1], vm.stack[5]);
+        assert_eq!(
+            memory_ops::bytes(&vm.memory, vm.stack[3], vm.stack[4]),
+            Ok(&b"second"[..])
+        );
+        let code = format!("s\" {}\"", "x".repeat(257));
+        assert_eq!(
+            vm.interpret(&code),
+            Err(ForthError::Parse(ParseError::StringTooLong))
+        );
+    }
+
+    #[test]
+    fn test_eval_key_and_accept() {
+        let mut vm = Forth::new();
+        vm.set_key_input(&b"yes please\nx"[..]);
+        vm.interpret("here 3 allot dup 3 accept key key? key key?")
+            .unwrap();
+        let addr = vm.stack[0];
+        assert_eq!(vm.stack[1..], [3, 120, 0, -1, 0]);
+        assert_eq!(memory_ops::bytes(&vm.memory, addr, 3), Ok(&b"yes"[..]));
+        vm.interpret("bl").unwrap();
+        assert_eq!(vm.stack.last(), Some(&32));
+    }
+
+    #[test]
+    fn test_eval_arithmetic_words() {
+        let code = ": CLAMP ( n lo hi -- n' ) rot min max ; 15 0 10 CLAMP -5 0 10 CLAMP";
+        assert_eq!(run_forth(code).unwrap(), vec![10, 0]);
+        let code = ": COUNT-TO-5 0 begin 1+ dup 5 >= until ;
+            COUNT-TO-5 3 1- -4 abs negate 7 0 10 within 0= 0<>";
+        assert_eq!(run_forth(code).unwrap(), vec![5, 2, -4, 0]);
+        // Scale by a fraction without overflowing: 3/4 of a large number
+        let code = "$7FFFFFFFFFFFFFFF 3 4 */ -7 2 /mod -7 -1 2 fm/mod -7 -1 2 sm/rem";
+        assert_eq!(
+            run_forth(code).unwrap(),
+            vec![i64::MAX / 4 * 3 + 2, -1, -3, 1, -4, -1, -3]
+        );
+    }
+
+    #[test]
+    fn test_eval_bitwise() {
+        // Pack two 16-bit fields into one cell and take them apart again
+        let code = ": PACK ( hi lo -- x ) swap 16 lshift or ;
+            : UNPACK ( x -- hi lo ) dup 16 rshift swap $FFFF and ;
+            $1234 $ABCD PACK dup UNPACK";
+        assert_eq!(run_forth(code).unwrap(), vec![0x1234ABCD, 0x1234, 0xABCD]);
+        let code = "true false true xor 0 invert 6 2* -7 2/ -8 1 arshift 1 64 lshift";
+        assert_eq!(run_forth(code).unwrap(), vec![-1, -1, -1, 12, -4, -4, 0]);
+    }
+
+    #[test]
+    fn test_eval_base() {
+        let mut vm = Forth::new();
+        vm.interpret("base @ hex base @ FF -ff binary 1010 decimal 10")
+            .unwrap();
+        assert_eq!(vm.stack, vec![10, 16, 255, -255, 10, 10]);
+        // A word in the dictionary wins over a number
+        let mut vm = Forth::new();
+        vm.interpret(": ADD + ; hex 1 2 ADD decimal 36 base ! zz")
+            .unwrap();
+        assert_eq!(vm.stack, vec![3, 35 * 36 + 35]);
+        // Digits outside the base are not numbers
+        let mut vm = Forth::new();
+        assert_eq!(
+            vm.interpret("binary 12"),
+            Err(ForthError::Eval(EvalError::UnknownWord("12".to_string())))
+        );
+        // Nor is anything in a base with no digit set
+        vm.interpret("decimal 99 base ! 12").unwrap();
+        assert_eq!(vm.stack, vec![12]);
+    }
+
+    #[test]
+    fn test_eval_pictured_output() {
+        let mut vm = Forth::new();
+        let code = ": .N ( n -- c-addr u ) dup 0 < swap dup 0 < if 0 swap - then
+            0 <# # 46 hold #s rot sign #> ; -1234 .N";
+        vm.interpret(code).unwrap();
+        let (addr, len) = (vm.stack[0], vm.stack[1]);
+        assert_eq!(memory_ops::bytes(&vm.memory, addr, len), Ok(&b"-123.4"[..]));
+        vm.interpret("2drop hex FF 0 <# #s #>").unwrap();
+        let (addr, len) = (vm.stack[0], vm.stack[1]);
+        assert_eq!(memory_ops::bytes(&vm.memory, addr, len), Ok(&b"FF"[..]));
+        // Printing words leave nothing behind
+        vm.interpret("2drop decimal 5 . 6 u. 7 3 .r 8 3 u.r")
+            .unwrap();
+        assert!(vm.stack.is_empty());
+    }
+
+    #[test]
+    fn test_eval_exit() {
+        assert_eq!(run_forth(": TEST 1 exit 2 ; TEST").unwrap(), vec![1]);
+        // EXIT only returns from the innermost word
+        let code = ": INNER 1 exit 2 ; : OUTER INNER 3 ; OUTER";
+        assert_eq!(run_forth(code).unwrap(), vec![1, 3]);
+        // From inside IF ... ELSE ... THEN
+        let code = ": SIGNUM dup 0 < if drop -1 exit then 0 > if 1 exit then 0 ; -5 SIGNUM 0 SIGNUM 7 SIGNUM";
+        assert_eq!(run_forth(code).unwrap(), vec![-1, 0, 1]);
+        // From inside BEGIN ... AGAIN, which has no other way out
+        let code = ": COUNT-UP 0 begin 1 + dup 5 = if exit then again ; COUNT-UP";
+        assert_eq!(run_forth(code).unwrap(), vec![5]);
+    }
+
+    #[test]
+    fn test_eval_exit_from_loops() {
+        let code = ": FIND-3 10 0 do i 3 = if i unloop exit then loop -1 ; FIND-3";
+        assert_eq!(run_forth(code).unwrap(), vec![3]);
+        let mut vm = Forth::new();
+        vm.interpret(": INNER 10 0 do 5 0 do i unloop unloop exit loop loop ;")
+            .unwrap();
+        vm.interpret(": OUTER 3 0 do INNER i loop ; OUTER").unwrap();
+        assert_eq!(vm.stack, vec![0, 0, 0, 1, 0, 2]);
+        assert!(vm.return_stack.is_empty());
+        // Without UNLOOP the loop parameters are still on the return stack
+        assert_eq!(
+            vm.interpret(": BAD 10 0 do exit loop ; BAD")
+                .unwrap_err()
+                .eval_error(),
+            Some(&EvalError::ReturnStackImbalance)
+        );
+        assert!(vm.return_stack.is_empty());
+    }
+
+    #[test]
+    fn test_eval_recurse() {
+        let code = ": FACT dup 1 > if dup 1 - recurse * then ; 5 FACT";
+        assert_eq!(run_forth(code).unwrap(), vec![120]);
+        let code = ": FIB dup 2 < if drop 1 exit then dup 1 - recurse swap 2 - recurse + ; 10 FIB";
+        assert_eq!(run_forth(code).unwrap(), vec![89]);
+    }
+
+    #[test]
+    fn test_eval_recurse_ignores_redefinition() {
+        let mut vm = Forth::new();
+        vm.interpret(": FACT dup 1 > if dup 1 - recurse * then ;")
+            .unwrap();
+        let old_fact = vm.word("FACT").unwrap().body.clone();
+        // An older FACT calling itself by name would now pick up this one
+        vm.interpret(": FACT drop 0 ;").unwrap();
+        vm.stack.push(5);
+        assert!(eval(&old_fact, &mut vm).is_ok());
+        assert_eq!(vm.stack, vec![120]);
+    }
+
+    #[test]
+    fn test_eval_backends_agree() {
+        let programs = [
+            ": T 0 > if 1 else -1 then ; 5 T -5 T",
+            ": T begin 1 - dup 0 = until ; 3 T",
+            ": T 0 swap begin dup while swap over + swap 1 - repeat drop ; 4 T",
+            ": T case 1 of 10 endof 2 of 20 endof 99 swap endcase ; 1 T 2 T 3 T",
+            ": T 10 0 do i dup 3 = if leave then drop loop ; T",
+            ": T 0 0 ?do 1 loop 10 0 do i 3 +loop ; T",
+            ": T 3 0 do 3 0 do i j * dup 2 = if unloop unloop exit then drop loop loop ; T",
+            ": T dup 1 > if dup 1 - recurse * then ; 6 T",
+            ": C create , does> @ 2 * ; 21 C X X",
+            ": T begin dup 5 < while 1 + dup 3 = if exit then repeat ; 0 T",
+            ": T 3 0 do 1 if ['] drop catch then loop ; T",
+        ];
+        for program in programs {
+            let mut tree = Forth::new();
+            tree.set_backend(Backend::Tree);
+            let mut flat = Forth::new();
+            assert!(flat.interpret(program).is_ok(), "{}", program);
+            assert!(tree.interpret(program).is_ok(), "{}", program);
+            assert_eq!(tree.stack, flat.stack, "{}", program);
+        }
+        // Control structures become jumps
+        let mut vm = Forth::new();
+        vm.interpret(": T if 1 then ;").unwrap();
+        assert_eq!(
+            *vm.word("T").unwrap().body,
+            [ForthOp::ZeroBranch(2), ForthOp::Push(1)]
+        );
+    }
+
+    #[test]
+    fn test_eval_flat_loop_errors() {
+        // Flat loop ops whose parameters were taken off the return stack
+        let ops = vec![ForthOp::LoopBranch(-1)];
+        let mut vm = Forth::new();
+        assert_eq!(eval(&ops, &mut vm), Err(EvalError::LoopStackUnderflow));
+        let ops = vec![ForthOp::LeaveBranch(1)];
+        assert_eq!(eval(&ops, &mut vm), Err(EvalError::LoopStackUnderflow));
+        // A jump past the end finishes the list
+        let ops = vec![ForthOp::Push(0), ForthOp::ZeroBranch(5), ForthOp::Push(1)];
+        assert!(eval(&ops, &mut vm).is_ok());
+        assert!(vm.stack.is_empty());
+    }
+
+    #[test]
+    fn test_eval_catch_throw() {
+        let mut vm = Forth::new();
+        vm.interpret(": SAFE/ ['] / catch ;").unwrap();
+        vm.interpret("10 2 SAFE/").unwrap();
+        assert_eq!(vm.stack, vec![5, 0]);
+        // The stack goes back to its depth at CATCH, with the code on top
+        vm.stack.clear();
+        vm.interpret("10 0 SAFE/").unwrap();
+        assert_eq!(vm.stack.len(), 3);
+        assert_eq!(vm.stack[2], -10);
+        vm.stack.clear();
+        vm.interpret(": T 1 2 3 throw ; ' T catch").unwrap();
+        assert_eq!(vm.stack, vec![3]);
+        vm.stack.clear();
+        vm.interpret("0 throw ' T catch 0 ' T catch").unwrap();
+        assert_eq!(vm.stack, vec![3, 0, 3]);
+        // Nested CATCHes: the inner one handles the error
+        vm.stack.clear();
+        vm.interpret(": OUTER ['] T catch 100 + ; ' OUTER catch")
+            .unwrap();
+        assert_eq!(vm.stack, vec![103, 0]);
+    }
+
+    #[test]
+    fn test_eval_catch_restores_return_stack() {
+        let mut vm = Forth::new();
+        vm.interpret(": R 1 >r 2 >r 10 0 do -1 throw loop ;")
+            .unwrap();
+        vm.interpret(": T 5 >r ['] R catch r> ; T").unwrap();
+        assert_eq!(vm.stack, vec![-1, 5]);
+        assert!(vm.return_stack.is_empty());
+        assert_eq!(vm.return_base, 0);
+    }
+
+    #[test]
+    fn test_eval_catch_errors() {
+        // Every error can be caught, with its standard code
+        let mut vm = Forth::new();
+        vm.interpret(": U drop ; : W later ; : F recurse ;")
+            .unwrap();
+        vm.interpret("' U catch ' W catch").unwrap();
+        assert_eq!(vm.stack, vec![-4, -13]);
+        vm.stack.clear();
+        vm.set_max_depth(50);
+        vm.interpret("' F catch").unwrap();
+        assert_eq!(vm.stack, vec![-5]);
+        // Uncaught, a code reads as the error it stands for
+        assert_eq!(
+            vm.interpret("-4 throw"),
+            Err(ForthError::Eval(EvalError::StackUnderflow))
+        );
+        assert_eq!(
+            vm.interpret("55 throw"),
+            Err(ForthError::Eval(EvalError::Throw(55)))
+        );
+        assert_eq!(
+            vm.interpret("1000 catch"),
+            Err(ForthError::Eval(EvalError::InvalidExecutionToken(1000)))
+        );
+    }
+
+    #[test]
+    fn test_eval_abort() {
+        let mut vm = Forth::new();
+        vm.interpret(": A abort ; : CHECK abort\" negative\" ;")
+            .unwrap();
+        vm.interpret("' A catch 0 ' CHECK catch 1 ' CHECK catch")
+            .unwrap();
+        assert_eq!(vm.stack, vec![-1, 0, 0, -2]);
+        // Uncaught, ABORT empties the data stack
+        assert_eq!(
+            vm.interpret("1 2 -1 CHECK").unwrap_err().eval_error(),
+            Some(&EvalError::AbortQuote("negative".to_string()))
+        );
+        assert!(vm.stack.is_empty());
+        assert_eq!(
+            vm.interpret("1 2 A").unwrap_err().eval_error(),
+            Some(&EvalError::Abort)
+        );
+        assert!(vm.stack.is_empty());
+    }
+
+    #[test]
+    fn test_eval_error_codes() {
+        assert_eq!(EvalError::DivisionByZero.code(), -10);
+        assert_eq!(EvalError::UnknownWord("X".to_string()).code(), -13);
+        assert_eq!(
+            EvalError::Compile(ParseError::MalformedNumber("9".repeat(20))).code(),
+            -24
+        );
+        assert_eq!(EvalError::InvalidAddress(-1).code(), -9);
+        assert_eq!(EvalError::Io("Broken pipe".to_string()).code(), -37);
+        assert_eq!(EvalError::Throw(7).code(), 7);
+        assert_eq!(EvalError::from_code(-26), EvalError::LoopStackUnderflow);
+        assert_eq!(EvalError::from_code(-9), EvalError::Throw(-9));
+        for error in [
+            EvalError::Abort,
+            EvalError::StackUnderflow,
+            EvalError::Overflow,
+        ] {
+            assert_eq!(EvalError::from_code(error.code()), error);
+        }
+    }
+
+    #[test]
+    fn test_eval_backtrace() {
+        for backend in [Backend::Bytecode, Backend::Tree] {
+            let mut vm = Forth::new();
+            vm.set_backend(backend);
+            vm.set_source("foo.fth");
+            vm.interpret(": SQUARE dup * ;").unwrap();
+            vm.interpret(": TEST").unwrap();
+            vm.interpret("  if SQUARE then ;").unwrap();
+            let error = vm.interpret("1 TEST").unwrap_err();
+            assert_eq!(error.eval_error(), Some(&EvalError::StackUnderflow));
+            assert_eq!(
+                error.to_string(),
+                "Error: Stack underflow in SQUARE (called from TEST at foo.fth:3:6, called at foo.fth:4:3)"
+            );
+            // Forward references know where they were written too
+            vm.interpret(": QUAD later ;").unwrap();
+            assert_eq!(
+                vm.interpret("QUAD").unwrap_err().to_string(),
+                "Error: Unknown word: later in QUAD at foo.fth:5:8 (called at foo.fth:6:1)"
+            );
+            // So do calls made by RECURSE
+            vm.interpret(": DOWN dup if 1- recurse then drop drop ;")
+                .unwrap();
+            assert_eq!(
+                vm.interpret("1 DOWN").unwrap_err().to_string(),
+                "Error: Stack underflow in DOWN (called from DOWN at foo.fth:7:18, called at foo.fth:8:3)"
+            );
+            // Errors outside any word come back as they are
+            assert_eq!(
+                vm.interpret("1 0 /"),
+                Err(ForthError::Eval(EvalError::DivisionByZero))
+            );
+        }
+    }
+
+    #[test]
+    fn test_eval_backtrace_limit() {
+        let mut vm = Forth::new();
+        vm.set_max_depth(100);
+        let error = vm.interpret(": FOREVER recurse ; FOREVER").unwrap_err();
+        let ForthError::Traced(EvalError::ReturnStackOverflow, backtrace) = error else {
+            panic!("expected a traced overflow, got {:?}", error);
+        };
+        assert_eq!(backtrace.entries.len(), MAX_TRACE);
+        assert_eq!(backtrace.more, 100 - MAX_TRACE);
+        assert!(
+            backtrace
+                .to_string()
+                .ends_with("called from FOREVER at <input>:1:11, and 84 more)")
+        );
+        // An error caught by CATCH leaves no backtrace behind
+        vm.interpret(": FAILS 1 0 / ; ' FAILS catch").unwrap();
+        assert_eq!(vm.stack, vec![-10]);
+    }
+
+    #[test]
+    fn test_eval_forward_reference() {
+        let mut vm = Forth::new();
+        // LATER is looked up when QUAD runs, so its latest definition counts
+        vm.interpret(": QUAD dup later ;").unwrap();
+        assert_eq!(
+            vm.interpret("2 QUAD").unwrap_err().eval_error(),
+            Some(&EvalError::UnknownWord("later".to_string()))
+        );
+        vm.stack.clear();
+        vm.interpret(": LATER + 2 * ; 3 QUAD").unwrap();
+        assert_eq!(vm.stack, vec![12]);
+        vm.interpret(": LATER * ; 3 QUAD").unwrap();
+        assert_eq!(vm.stack, vec![12, 9]);
+    }
+
+    #[test]
+    fn test_eval_redefinition_keeps_callers() {
+        // Words that were defined when a definition was compiled stay bound
+        let mut vm = Forth::new();
+        vm.interpret(": A 1 ; : B A 10 + ; : A 2 ; B A").unwrap();
+        assert_eq!(vm.stack, vec![11, 2]);
+    }
+
+    #[test]
+    fn test_eval_deep_recursion() {
+        // Far deeper than the Rust stack would allow one call per word
+        let code = ": SUM dup 0 > if dup 1 - recurse + then ; 200000 SUM";
+        assert_eq!(run_forth(code).unwrap(), vec![20000100000]);
+        let code = ": DOWN begin dup while 1 - dup if recurse then repeat ; 100000 DOWN";
+        assert_eq!(run_forth(code).unwrap(), vec![0]);
+    }
+
+    #[test]
+    fn test_eval_return_stack_overflow() {
+        let mut vm = Forth::new();
+        vm.set_max_depth(100);
+        assert_eq!(
+            vm.interpret(": FOREVER 1 >r recurse ; FOREVER")
+                .unwrap_err()
+                .eval_error(),
+            Some(&EvalError::ReturnStackOverflow)
+        );
+        assert!(vm.return_stack.is_empty());
+        assert_eq!(vm.return_base, 0);
+        // A deferred word that runs itself never reaches a body
+        assert_eq!(
+            vm.interpret("defer D ' D is D D"),
+            Err(ForthError::Eval(EvalError::ReturnStackOverflow))
+        );
+        // The VM is still usable, and 100 frames are enough for shallow words
+        vm.stack.clear();
+        vm.interpret(": SUM dup 0 > if dup 1 - recurse + then ; 10 SUM")
+            .unwrap();
+        assert_eq!(vm.stack, vec![55]);
+    }
+
+    #[test]
+    fn test_eval_max_depth_same_in_both_backends() {
+        // Only calls count, not the IF and BEGIN frames of the tree backend
+        for backend in [Backend::Bytecode, Backend::Tree] {
+            let mut vm = Forth::new();
+            vm.set_backend(backend);
+            vm.set_max_depth(100);
+            vm.interpret(": DEEP begin 1+ dup if recurse then 0 until ;")
+                .unwrap();
+            let error = vm.interpret("0 DEEP").unwrap_err();
+            assert_eq!(error.eval_error(), Some(&EvalError::ReturnStackOverflow));
+            // The ops given to eval are the first of the 100
+            assert_eq!(vm.stack, vec![99]);
+            // Every entry knows where its RECURSE was, the innermost too
+            let text = error.to_string();
+            assert!(text.starts_with("Error: Return stack overflow in DEEP at <input>:1:24 ("));
+            assert!(text.ends_with("called from DEEP at <input>:1:24, and 84 more)"));
+        }
+    }
+
+    #[test]
+    fn test_eval_error_loop_stack_underflow_loop() {
+        // LOOP without DO - This should be a ParseError now, but test eval robustness
+        let ops = vec![ForthOp::Loop];
+        let mut vm = Forth::new();
+        let result = eval(&ops, &mut vm);
+        assert_eq!(result, Err(EvalError::LoopStackUnderflow));
+    }
+
+    #[test]
+    fn test_eval_error_loop_stack_underflow_i() {
+        // I without DO
+        let ops = vec![ForthOp::I];
+        let mut vm = Forth::new();
+        let result = eval(&ops, &mut vm);
         assert_eq!(result, Err(EvalError::LoopStackUnderflow));
     }
 
@@ -689,18 +2664,11 @@ mod tests {
             vec![ForthOp::Push(2), ForthOp::Multiply],
             true, // Immediate flag set to true
         )];
-        let (mut stack, mut dict, mut loop_stack) = default_eval_state();
-        let mut latest_word = None;
-        let result = eval(
-            &ops,
-            &mut stack,
-            &mut dict,
-            &mut loop_stack,
-            &mut latest_word,
-        );
+        let mut vm = Forth::new();
+        let result = eval(&ops, &mut vm);
         assert!(result.is_ok());
-        assert!(dict.contains_key("DOUBLE"));
-        assert!(dict["DOUBLE"].immediate); // Check that immediate flag is set
+        assert!(vm.dictionary.contains_key("DOUBLE"));
+        assert!(vm.word("DOUBLE").unwrap().immediate); // Check that immediate flag is set
     }
 
     #[test]
@@ -713,18 +2681,12 @@ mod tests {
             ),
             ForthOp::Immediate, // Mark as immediate after definition
         ];
-        let (mut stack, mut dict, mut loop_stack) = default_eval_state();
-        let mut latest_word = Some("DOUBLE".to_string()); // Simulate having just defined DOUBLE
-        let result = eval(
-            &ops,
-            &mut stack,
-            &mut dict,
-            &mut loop_stack,
-            &mut latest_word,
-        );
+        let mut vm = Forth::new();
+        vm.latest_word = Some("DOUBLE".to_string()); // Simulate having just defined DOUBLE
+        let result = eval(&ops, &mut vm);
         assert!(result.is_ok());
-        assert!(dict.contains_key("DOUBLE"));
-        assert!(dict["DOUBLE"].immediate); // Check that immediate flag is set after IMMEDIATE
+        assert!(vm.dictionary.contains_key("DOUBLE"));
+        assert!(vm.word("DOUBLE").unwrap().immediate); // Check that immediate flag is set after IMMEDIATE
     }
 
     #[test]
@@ -733,17 +2695,10 @@ mod tests {
         let ops = vec![
             ForthOp::Push(5),
             ForthOp::Push(0),
-            ForthOp::Word("do".to_string()),
+            ForthOp::Word("do".to_string(), None),
         ];
-        let (mut stack, mut dict, mut loop_stack) = default_eval_state();
-        let mut latest_word = None;
-        let result = eval(
-            &ops,
-            &mut stack,
-            &mut dict,
-            &mut loop_stack,
-            &mut latest_word,
-        );
+        let mut vm = Forth::new();
+        let result = eval(&ops, &mut vm);
         assert_eq!(result, Err(EvalError::CompileOnlyWord("do".to_string())));
     }
 
@@ -782,7 +2737,7 @@ mod tests {
         assert!(result.is_err());
         assert_eq!(
             result.err().unwrap(),
-            TestError::Eval(EvalError::UnknownWord("foo".to_string()))
+            ForthError::Eval(EvalError::UnknownWord("foo".to_string()))
         );
     }
 
@@ -792,7 +2747,7 @@ mod tests {
         let result_do = run_forth("1 2 do");
         assert!(result_do.is_err());
         match result_do.err().unwrap() {
-            TestError::Parse(ParseError::ControlWordOutsideDefinition(s)) => assert_eq!(s, "do"),
+            ForthError::Parse(ParseError::ControlWordOutsideDefinition(s)) => assert_eq!(s, "do"),
             other => panic!(
                 "Expected ParseError::ControlWordOutsideDefinition for 'do', got {:?}",
                 other
@@ -803,7 +2758,7 @@ mod tests {
         let result_loop = run_forth("loop");
         assert!(result_loop.is_err());
         match result_loop.err().unwrap() {
-            TestError::Parse(ParseError::ControlWordOutsideDefinition(s)) => assert_eq!(s, "loop"),
+            ForthError::Parse(ParseError::ControlWordOutsideDefinition(s)) => assert_eq!(s, "loop"),
             other => panic!(
                 "Expected ParseError::ControlWordOutsideDefinition for 'loop', got {:?}",
                 other
@@ -814,7 +2769,7 @@ mod tests {
         let result_if = run_forth("1 if 2 then");
         assert!(result_if.is_err());
         match result_if.err().unwrap() {
-            TestError::Parse(ParseError::ControlWordOutsideDefinition(s)) => assert_eq!(s, "if"),
+            ForthError::Parse(ParseError::ControlWordOutsideDefinition(s)) => assert_eq!(s, "if"),
             other => panic!(
                 "Expected ParseError::ControlWordOutsideDefinition for 'if', got {:?}",
                 other
@@ -825,7 +2780,7 @@ mod tests {
         let result_then = run_forth("1 2 then");
         assert!(result_then.is_err());
         match result_then.err().unwrap() {
-            TestError::Parse(ParseError::ControlWordOutsideDefinition(s)) => assert_eq!(s, "then"),
+            ForthError::Parse(ParseError::ControlWordOutsideDefinition(s)) => assert_eq!(s, "then"),
             other => panic!(
                 "Expected ParseError::ControlWordOutsideDefinition for 'then', got {:?}",
                 other
@@ -836,7 +2791,7 @@ mod tests {
         let result_else = run_forth("1 2 else");
         assert!(result_else.is_err());
         match result_else.err().unwrap() {
-            TestError::Parse(ParseError::ControlWordOutsideDefinition(s)) => assert_eq!(s, "else"),
+            ForthError::Parse(ParseError::ControlWordOutsideDefinition(s)) => assert_eq!(s, "else"),
             other => panic!(
                 "Expected ParseError::ControlWordOutsideDefinition for 'else', got {:?}",
                 other
@@ -0,0 +1,266 @@
+use crate::eval::EvalError;
+use crate::forth::{HOLD_ADDR, HOLD_SIZE};
+use crate::io_ops;
+use crate::memory_ops;
+use std::io::{self, Write};
+
+// The digits of u in base, most significant first, using 0-9 then A-Z
+fn digits(mut u: u128, base: u32) -> String {
+    let mut out = Vec::new();
+    loop {
+        out.push(std::char::from_digit((u % base as u128) as u32, base).unwrap());
+        u /= base as u128;
+        if u == 0 {
+            break;
+        }
+    }
+    out.iter().rev().collect::<String>().to_uppercase()
+}
+
+// How . and .S show a cell: signed, in base
+pub fn signed(n: i64, base: u32) -> String {
+    let text = digits(n.unsigned_abs() as u128, base);
+    if n < 0 { format!("-{}", text) } else { text }
+}
+
+pub fn unsigned(u: i64, base: u32) -> String {
+    digits(u as u64 as u128, base)
+}
+
+// ( n -- ) Print n followed by a space
+pub fn dot(stack: &mut Vec<i64>, base: u32, out: &mut impl Write) -> Result<(), EvalError> {
+    let n = stack.pop().ok_or(EvalError::StackUnderflow)?;
+    io_ops::write_bytes(out, format!("{} ", signed(n, base)).as_bytes())
+}
+
+// ( u -- )
+pub fn u_dot(stack: &mut Vec<i64>, base: u32, out: &mut impl Write) -> Result<(), EvalError> {
+    let u = stack.pop().ok_or(EvalError::StackUnderflow)?;
+    io_ops::write_bytes(out, format!("{} ", unsigned(u, base)).as_bytes())
+}
+
+// ( -- ) Print the depth and the whole stack, bottom first, leaving it as is
+pub fn dot_s(stack: &[i64], base: u32, out: &mut impl Write) -> Result<(), EvalError> {
+    let mut text = format!("Stack: <{}> ", stack.len());
+    for item in stack {
+        text.push_str(&signed(*item, base));
+        text.push(' ');
+    }
+    text.push('\n');
+    io_ops::write_bytes(out, text.as_bytes())
+}
+
+// Write text right-aligned in a field of width characters. The padding is
+// written like SPACES, so a width past MAX_SPACES pads only that much.
+fn write_right(out: &mut impl Write, text: &str, width: i64) -> io::Result<()> {
+    io_ops::write_spaces(out, width.saturating_sub(text.len() as i64))?;
+    out.write_all(text.as_bytes())
+}
+
+// ( n width -- ) Print n right-aligned in a field of width characters, with
+// no trailing space. A number wider than the field is printed in full.
+pub fn dot_r(stack: &mut Vec<i64>, base: u32, out: &mut impl Write) -> Result<(), EvalError> {
+    let width = stack.pop().ok_or(EvalError::StackUnderflow)?;
+    let n = stack.pop().ok_or(EvalError::StackUnderflow)?;
+    Ok(write_right(out, &signed(n, base), width)?)
+}
+
+// ( u width -- )
+pub fn u_dot_r(stack: &mut Vec<i64>, base: u32, out: &mut impl Write) -> Result<(), EvalError> {
+    let width = stack.pop().ok_or(EvalError::StackUnderflow)?;
+    let u = stack.pop().ok_or(EvalError::StackUnderflow)?;
+    Ok(write_right(out, &unsigned(u, base), width)?)
+}
+
+// Pictured output builds the text right to left in `hold`, last character
+// first, as a double-cell number ud (low cell below, high cell on top) is
+// divided down one digit at a time.
+
+fn pop_ud(stack: &mut Vec<i64>) -> Result<u128, EvalError> {
+    let hi = stack.pop().ok_or(EvalError::StackUnderflow)?;
+    let lo = stack.pop().ok_or(EvalError::StackUnderflow)?;
+    Ok(((hi as u64 as u128) << 64) | lo as u64 as u128)
+}
+
+fn push_ud(stack: &mut Vec<i64>, ud: u128) {
+    stack.push(ud as u64 as i64);
+    stack.push((ud >> 64) as u64 as i64);
+}
+
+fn add_to_hold(hold: &mut Vec<u8>, c: u8) -> Result<(), EvalError> {
+    if hold.len() >= HOLD_SIZE {
+        return Err(EvalError::PicturedOutputOverflow);
+    }
+    hold.push(c);
+    Ok(())
+}
+
+// ( -- ) <#
+pub fn less_number_sign(hold: &mut Vec<u8>) -> Result<(), EvalError> {
+    hold.clear();
+    Ok(())
+}
+
+// ( ud1 -- ud2 ) # : add the lowest digit of ud1
+pub fn number_sign(stack: &mut Vec<i64>, hold: &mut Vec<u8>, base: u32) -> Result<(), EvalError> {
+    let ud = pop_ud(stack)?;
+    add_to_hold(hold, digits(ud % base as u128, base).as_bytes()[0])?;
+    push_ud(stack, ud / base as u128);
+    Ok(())
+}
+
+// ( ud -- 0 0 ) #S : add digits until nothing is left, at least one
+pub fn number_sign_s(stack: &mut Vec<i64>, hold: &mut Vec<u8>, base: u32) -> Result<(), EvalError> {
+    loop {
+        number_sign(stack, hold, base)?;
+        if stack[stack.len() - 2..] == [0, 0] {
+            return Ok(());
+        }
+    }
+}
+
+// ( char -- )
+pub fn hold_char(stack: &mut Vec<i64>, hold: &mut Vec<u8>) -> Result<(), EvalError> {
+    let c = stack.pop().ok_or(EvalError::StackUnderflow)?;
+    add_to_hold(hold, c as u8)
+}
+
+// ( n -- ) Add a minus sign if n is negative
+pub fn sign(stack: &mut Vec<i64>, hold: &mut Vec<u8>) -> Result<(), EvalError> {
+    let n = stack.pop().ok_or(EvalError::StackUnderflow)?;
+    if n < 0 {
+        add_to_hold(hold, b'-')?;
+    }
+    Ok(())
+}
+
+// ( ud -- c-addr u ) #> : drop ud and leave the text in the system's
+// pictured output buffer, where the next <# ... #> overwrites it
+pub fn number_sign_greater(
+    stack: &mut Vec<i64>,
+    hold: &[u8],
+    memory: &mut [u8],
+) -> Result<(), EvalError> {
+    pop_ud(stack)?;
+    let len = hold.len() as i64;
+    let buffer = memory_ops::bytes_mut(memory, HOLD_ADDR, len)?;
+    for (dest, c) in buffer.iter_mut().zip(hold.iter().rev()) {
+        *dest = *c;
+    }
+    stack.push(HOLD_ADDR);
+    stack.push(len);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signed_and_unsigned() {
+        assert_eq!(signed(255, 16), "FF");
+        assert_eq!(signed(-255, 16), "-FF");
+        assert_eq!(signed(0, 2), "0");
+        assert_eq!(signed(10, 2), "1010");
+        assert_eq!(signed(35, 36), "Z");
+        assert_eq!(signed(i64::MIN, 10), "-9223372036854775808");
+        assert_eq!(unsigned(-1, 16), "FFFFFFFFFFFFFFFF");
+        assert_eq!(unsigned(-1, 10), "18446744073709551615");
+    }
+
+    #[test]
+    fn test_write_right() {
+        let mut out = Vec::new();
+        write_right(&mut out, "-42", 6).unwrap();
+        write_right(&mut out, "12345", 2).unwrap();
+        write_right(&mut out, "7", i64::MIN).unwrap();
+        assert_eq!(out, b"   -42123457");
+        // A huge width is clamped like SPACES
+        let mut out = Vec::new();
+        write_right(&mut out, "10", i64::MAX).unwrap();
+        assert_eq!(out.len() as i64, io_ops::MAX_SPACES + 2);
+        assert!(out.ends_with(b" 10"));
+        // A failed write is an error rather than a panic
+        let mut full = [0u8; 100];
+        assert!(write_right(&mut &mut full[..], "10", 200).is_err());
+        assert_eq!(full, [b' '; 100]);
+    }
+
+    #[test]
+    fn test_print_stack_effects() {
+        let mut out = Vec::new();
+        let mut s = vec![1, 2, -3, 4];
+        assert!(dot_s(&s, 10, &mut out).is_ok());
+        assert!(dot_r(&mut s, 10, &mut out).is_ok());
+        assert!(u_dot(&mut s, 10, &mut out).is_ok());
+        assert!(dot(&mut s, 16, &mut out).is_ok());
+        assert!(s.is_empty());
+        assert_eq!(out, b"Stack: <4> 1 2 -3 4 \n  -32 1 ");
+        assert_eq!(dot(&mut s, 10, &mut out), Err(EvalError::StackUnderflow));
+        assert_eq!(
+            u_dot_r(&mut vec![1], 10, &mut out),
+            Err(EvalError::StackUnderflow)
+        );
+    }
+
+    #[test]
+    fn test_print_write_error() {
+        // . reports a failed write instead of panicking like print!
+        let mut full = [0u8; 2];
+        let mut s = vec![123];
+        assert_eq!(
+            dot(&mut s, 10, &mut &mut full[..]),
+            Err(EvalError::Io("failed to write whole buffer".to_string()))
+        );
+        assert!(s.is_empty());
+        assert!(dot_s(&[1, 2], 10, &mut &mut full[..]).is_err());
+    }
+
+    #[test]
+    fn test_pictured_output() {
+        let mut memory = vec![0; HOLD_ADDR as usize + HOLD_SIZE];
+        let mut hold = Vec::new();
+        // -42 as ( n ud ): SIGN uses n once the digits are done
+        let mut s = vec![-42, 42, 0];
+        assert!(less_number_sign(&mut hold).is_ok());
+        assert!(number_sign(&mut s, &mut hold, 10).is_ok());
+        assert_eq!(s, vec![-42, 4, 0]);
+        assert!(hold_char(&mut vec![b'.' as i64], &mut hold).is_ok());
+        assert!(number_sign_s(&mut s, &mut hold, 10).is_ok());
+        assert_eq!(s, vec![-42, 0, 0]);
+        let mut n = vec![s.remove(0)];
+        assert!(sign(&mut n, &mut hold).is_ok());
+        assert!(number_sign_greater(&mut s, &hold, &mut memory).is_ok());
+        assert_eq!(s, vec![HOLD_ADDR, 4]);
+        assert_eq!(&memory[HOLD_ADDR as usize..HOLD_ADDR as usize + 4], b"-4.2");
+    }
+
+    #[test]
+    fn test_pictured_output_double() {
+        // 2^64 needs the high cell
+        let mut memory = vec![0; HOLD_ADDR as usize + HOLD_SIZE];
+        let mut hold = Vec::new();
+        let mut s = vec![0, 1];
+        assert!(number_sign_s(&mut s, &mut hold, 16).is_ok());
+        assert!(number_sign_greater(&mut s, &hold, &mut memory).is_ok());
+        assert_eq!(s, vec![HOLD_ADDR, 17]);
+        assert_eq!(
+            &memory[HOLD_ADDR as usize..HOLD_ADDR as usize + 17],
+            b"10000000000000000"
+        );
+    }
+
+    #[test]
+    fn test_pictured_output_errors() {
+        let mut hold = vec![b'0'; HOLD_SIZE];
+        assert_eq!(
+            hold_char(&mut vec![b'1' as i64], &mut hold),
+            Err(EvalError::PicturedOutputOverflow)
+        );
+        assert_eq!(
+            number_sign(&mut vec![1], &mut Vec::new(), 10),
+            Err(EvalError::StackUnderflow)
+        );
+        assert_eq!(sign(&mut vec![], &mut hold), Err(EvalError::StackUnderflow));
+    }
+}
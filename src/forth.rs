@@ -0,0 +1,610 @@
+use crate::bytecode::Backend;
+use crate::eval::{Backtrace, DictEntry, EvalError, NativeWord, WordKind, eval};
+use crate::io_ops::KeyInput;
+use crate::memory_ops;
+use crate::number_ops::Overflow;
+use crate::parser::{
+    Compiler, ForthOp, ParseError, close_definition, continue_definition, parse, parse_next,
+    primitive_op,
+};
+use crate::token::{self, Location, Token};
+use std::collections::HashMap;
+use std::fmt;
+use std::io::Read;
+use std::rc::Rc;
+use std::vec;
+
+// Any error that can come out of interpreting Forth source
+#[derive(Debug, PartialEq)]
+pub enum ForthError {
+    Parse(ParseError),
+    Eval(EvalError),
+    // An error inside a word, with the words that were running when it happened
+    Traced(EvalError, Backtrace),
+}
+
+impl fmt::Display for ForthError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ForthError::Parse(e) => write!(f, "Parse Error: {:?}", e),
+            ForthError::Eval(e) => write!(f, "Error: {}", e),
+            ForthError::Traced(e, backtrace) => write!(f, "Error: {}{}", e, backtrace),
+        }
+    }
+}
+
+impl std::error::Error for ForthError {}
+
+impl ForthError {
+    // The error from running code, whether or not it came with a backtrace
+    pub fn eval_error(&self) -> Option<&EvalError> {
+        match self {
+            ForthError::Eval(e) | ForthError::Traced(e, _) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<ParseError> for ForthError {
+    fn from(error: ParseError) -> Self {
+        ForthError::Parse(error)
+    }
+}
+
+impl From<EvalError> for ForthError {
+    fn from(error: EvalError) -> Self {
+        ForthError::Eval(error)
+    }
+}
+
+// System variables sit at the start of the data space, below anything a
+// program allots
+pub(crate) const STATE_ADDR: i64 = 0; // STATE: true while compiling
+pub(crate) const BASE_ADDR: i64 = 8; // BASE: the radix for number input and output
+pub(crate) const HOLD_ADDR: i64 = 16; // Where #> leaves pictured numeric output
+pub(crate) const HOLD_SIZE: usize = 256;
+// Interpreted S" and C" take turns with two transient buffers, so the text
+// of the last two stays valid without using up data space
+pub(crate) const STRING_ADDR: i64 = HOLD_ADDR + HOLD_SIZE as i64;
+pub(crate) const STRING_SIZE: usize = 256;
+const SYSTEM_SIZE: usize = STRING_ADDR as usize + 2 * STRING_SIZE;
+
+// How deep calls to words may nest before EvalError::ReturnStackOverflow,
+// unless set_max_depth changes it. The ops given to eval count as one.
+pub const DEFAULT_MAX_DEPTH: usize = 1_000_000;
+
+// What locations name as their source until set_source changes it
+pub const DEFAULT_SOURCE: &str = "<input>";
+
+// The Forth virtual machine: owns the data stack, the dictionary and the
+// interpreter state that used to be threaded by hand through `eval`.
+#[derive(Debug)]
+pub struct Forth {
+    pub(crate) stack: Vec<i64>,
+    pub(crate) memory: Vec<u8>, // Byte-addressable data space; HERE is its length
+    pub(crate) dictionary: HashMap<String, usize>, // Name -> execution token of its latest definition
+    pub(crate) entries: Vec<DictEntry>, // Every definition made, indexed by execution token
+    primitives: HashMap<String, usize>, // Name -> execution token given to a built-in word
+    pub(crate) return_stack: Vec<i64>,  // >R data and DO loop parameters (limit, index)
+    pub(crate) return_base: usize,      // Start of the running word's frame on the return stack
+    pub(crate) latest_word: Option<String>, // Latest defined word, for IMMEDIATE
+    pub(crate) input: vec::IntoIter<Token>, // Rest of the line, for defining words that parse a name
+    pub(crate) compiler: Option<Compiler>,  // The definition being compiled, if any
+    pub(crate) key_input: KeyInput,         // Where KEY and ACCEPT read from
+    pub(crate) overflow: Overflow,          // Whether arithmetic wraps or reports overflow
+    pub(crate) max_depth: usize,            // Most calls the inner interpreter may have nested
+    pub(crate) backend: Backend,            // How new definitions are compiled
+    pub(crate) hold: Vec<u8>,               // Pictured numeric output so far, last character first
+    pub(crate) string_buffer: usize, // Which transient buffer the next interpreted string uses
+    pub(crate) backtrace: Backtrace, // Where the last error that escaped eval happened
+    locations: Vec<Location>, // Where each token being interpreted was read; empty if not known
+    source: Rc<str>,          // The name locations give for the lines interpreted
+    line: usize,              // How many lines of the source have been interpreted
+}
+
+impl Default for Forth {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Forth {
+    pub fn new() -> Self {
+        let mut memory = vec![0; SYSTEM_SIZE];
+        memory_ops::write_cell(&mut memory, BASE_ADDR, 10).unwrap();
+        Forth {
+            stack: Vec::new(),
+            memory,
+            dictionary: HashMap::new(),
+            entries: Vec::new(),
+            primitives: HashMap::new(),
+            return_stack: Vec::new(),
+            return_base: 0,
+            latest_word: None,
+            input: Vec::new().into_iter(),
+            compiler: None,
+            key_input: KeyInput::default(),
+            overflow: Overflow::default(),
+            max_depth: DEFAULT_MAX_DEPTH,
+            backend: Backend::default(),
+            hold: Vec::new(),
+            string_buffer: 0,
+            backtrace: Backtrace::default(),
+            locations: Vec::new(),
+            source: Rc::from(DEFAULT_SOURCE),
+            line: 0,
+        }
+    }
+
+    // Interpret one line of source. Each word runs as soon as it is parsed, so
+    // definitions and IMMEDIATE take effect for the rest of the line. A definition
+    // left open at the end of the line stays open, and later lines add to it.
+    pub fn interpret(&mut self, line: &str) -> Result<(), ForthError> {
+        let first_line = self.line + 1;
+        self.line += 1 + line.matches('\n').count();
+        let (tokens, locations) = match token::lex_located(line, &self.source, first_line) {
+            Ok(lexed) => lexed,
+            // Like a parse error, this drops any unfinished definition
+            Err(e) => {
+                close_definition(self);
+                return Err(e.into());
+            }
+        };
+        // A word run from here may interpret source of its own
+        let outer = std::mem::replace(&mut self.locations, locations);
+        let result = self.interpret_tokens(tokens.into_iter());
+        self.locations = outer;
+        result
+    }
+
+    fn interpret_tokens(&mut self, mut token_iter: vec::IntoIter<Token>) -> Result<(), ForthError> {
+        // Finish a definition left open by an earlier line first
+        let mut open = self.is_pending();
+        loop {
+            let next = if std::mem::take(&mut open) {
+                continue_definition(&mut token_iter, self).map(Some)
+            } else {
+                parse_next(&mut token_iter, self)
+            };
+            match next {
+                Ok(Some(op)) => {
+                    // Words run from here may read names from the rest of the line
+                    self.input = token_iter;
+                    let result = eval(std::slice::from_ref(&op), self);
+                    token_iter = std::mem::take(&mut self.input);
+                    if let Err(EvalError::Abort | EvalError::AbortQuote(_)) = result {
+                        // Nothing caught it, so ABORT also empties the data stack
+                        self.stack.clear();
+                    }
+                    result.map_err(|e| self.traced(e))?
+                }
+                Ok(None) => return Ok(()),
+                // Still inside a definition, wait for more lines
+                Err(ParseError::UnterminatedDefinition) => return Ok(()),
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    // True while a multi-line definition is waiting for more input
+    pub fn is_pending(&self) -> bool {
+        self.compiler.is_some()
+    }
+
+    // Signal the end of the input. Reports a definition that was never
+    // terminated, and DO/LOOP structures that were left open.
+    pub fn finish(&mut self) -> Result<(), ForthError> {
+        if self.is_pending() {
+            close_definition(self);
+            return Err(ParseError::UnterminatedDefinition.into());
+        }
+        if !self.return_stack.is_empty() {
+            return Err(EvalError::ControlStructureMismatch.into());
+        }
+        Ok(())
+    }
+
+    // Define (or redefine) a colon word from Forth source, as if by `: name source ;`
+    pub fn define(&mut self, name: &str, source: &str) -> Result<(), ForthError> {
+        let mut tokens = vec![Token::Colon, Token::Word(name.to_string())];
+        tokens.extend(token::lex(source)?);
+        tokens.push(Token::Semicolon);
+        // These tokens have no locations, even if a line is being interpreted,
+        // and a definition interpret left open waits until they are compiled
+        let outer = std::mem::take(&mut self.locations);
+        let open = self.compiler.take();
+        let ops = parse(tokens, self);
+        self.locations = outer;
+        if open.is_some() {
+            self.compiler = open;
+            self.set_state(true);
+        }
+        self.run(&ops?)
+    }
+
+    // Register a native word backed by a Rust closure. It lives in the same
+    // dictionary as colon definitions and can be redefined like them.
+    pub fn register<F>(&mut self, name: &str, func: F)
+    where
+        F: Fn(&mut Forth) -> Result<(), EvalError> + 'static,
+    {
+        let name = name.to_uppercase();
+        let native = NativeWord {
+            name: name.clone(),
+            func: Rc::new(func),
+        };
+        self.add_entry(DictEntry {
+            name,
+            body: Rc::from([ForthOp::Native(native)]),
+            immediate: false,
+            kind: WordKind::Colon,
+        });
+    }
+
+    // Evaluate already parsed operations against this VM
+    pub fn run(&mut self, ops: &[ForthOp]) -> Result<(), ForthError> {
+        eval(ops, self).map_err(|e| self.traced(e))
+    }
+
+    // An error that escaped eval, with the words it escaped from if there
+    // were any
+    fn traced(&mut self, error: EvalError) -> ForthError {
+        let backtrace = std::mem::take(&mut self.backtrace);
+        if backtrace.in_word() {
+            ForthError::Traced(error, backtrace)
+        } else {
+            ForthError::Eval(error)
+        }
+    }
+
+    // The data stack, bottom first
+    pub fn stack(&self) -> &[i64] {
+        &self.stack
+    }
+
+    pub fn push(&mut self, value: i64) {
+        self.stack.push(value);
+    }
+
+    pub fn pop(&mut self) -> Result<i64, EvalError> {
+        self.stack.pop().ok_or(EvalError::StackUnderflow)
+    }
+
+    // The data space, from address 0 up to HERE. It starts with the system
+    // variables such as STATE and BASE.
+    pub fn memory(&self) -> &[u8] {
+        &self.memory
+    }
+
+    // Read KEY and ACCEPT input from `reader` instead of standard input
+    pub fn set_key_input(&mut self, reader: impl Read + 'static) {
+        self.key_input = KeyInput::new(reader);
+    }
+
+    // Choose between wrapping arithmetic, the default, and checked
+    // arithmetic that fails with EvalError::Overflow
+    pub fn set_overflow(&mut self, overflow: Overflow) {
+        self.overflow = overflow;
+    }
+
+    // Limit how deep calls to words may nest. Going deeper
+    // fails with EvalError::ReturnStackOverflow instead of using more memory.
+    pub fn set_max_depth(&mut self, depth: usize) {
+        self.max_depth = depth;
+    }
+
+    // Compile new definitions to flat bytecode, the default, or keep them as
+    // parsed for the tree-walking evaluator. Existing words keep their form.
+    pub fn set_backend(&mut self, backend: Backend) {
+        self.backend = backend;
+    }
+
+    // Name the source of the lines interpreted from now on, such as a file
+    // name, for error locations. Line numbers start again from 1.
+    pub fn set_source(&mut self, source: &str) {
+        self.source = Rc::from(source);
+        self.line = 0;
+    }
+
+    // Where the token just read came from, given how many tokens are left
+    // after it. None for tokens that were not read by interpret.
+    pub(crate) fn token_location(&self, remaining: usize) -> Option<Location> {
+        let index = self.locations.len().checked_sub(remaining + 1)?;
+        self.locations.get(index).cloned()
+    }
+
+    // Keep the STATE variable in step with the compiler
+    pub(crate) fn set_state(&mut self, compiling: bool) {
+        let flag: i64 = if compiling { -1 } else { 0 };
+        // A program that ALLOTs below its own data may have released the cell
+        let _ = memory_ops::write_cell(&mut self.memory, STATE_ADDR, flag);
+    }
+
+    // The radix in BASE. Values that no digit set fits (outside 2 to 36)
+    // count as decimal.
+    pub(crate) fn base(&self) -> u32 {
+        match memory_ops::read_cell(&self.memory, BASE_ADDR) {
+            Ok(base @ 2..=36) => base as u32,
+            _ => 10,
+        }
+    }
+
+    // Look up a dictionary entry by name (case-insensitive)
+    pub fn word(&self, name: &str) -> Option<&DictEntry> {
+        self.lookup(&name.to_uppercase())
+    }
+
+    // Add a definition and point its name at it. Older definitions of the same
+    // name keep their execution tokens. Returns the new execution token.
+    pub(crate) fn add_entry(&mut self, entry: DictEntry) -> usize {
+        let xt = self.entries.len();
+        self.dictionary.insert(entry.name.clone(), xt);
+        self.latest_word = Some(entry.name.clone());
+        self.entries.push(entry);
+        xt
+    }
+
+    // The execution token for an (uppercase) name. A built-in word gets an
+    // entry the first time its token is asked for, but no name in the
+    // dictionary, so it still compiles inline and `word` does not find it.
+    pub(crate) fn find_xt(&mut self, name: &str) -> Option<usize> {
+        if let Some(&xt) = self.dictionary.get(name) {
+            return Some(xt);
+        }
+        if let Some(&xt) = self.primitives.get(name) {
+            return Some(xt);
+        }
+        let op = primitive_op(name)?;
+        let xt = self.entries.len();
+        self.entries.push(DictEntry {
+            name: name.to_string(),
+            body: Rc::from([op]),
+            immediate: false,
+            kind: WordKind::Colon,
+        });
+        self.primitives.insert(name.to_string(), xt);
+        Some(xt)
+    }
+
+    // The current definition of an (uppercase) name
+    pub(crate) fn lookup(&self, name: &str) -> Option<&DictEntry> {
+        self.dictionary.get(name).map(|&xt| &self.entries[xt])
+    }
+
+    pub(crate) fn lookup_xt(&self, name: &str) -> Option<usize> {
+        self.dictionary.get(name).copied()
+    }
+
+    pub(crate) fn lookup_mut(&mut self, name: &str) -> Option<&mut DictEntry> {
+        self.dictionary.get(name).map(|&xt| &mut self.entries[xt])
+    }
+
+    // The definition behind an execution token taken from the data stack
+    pub(crate) fn entry(&self, xt: i64) -> Result<&DictEntry, EvalError> {
+        usize::try_from(xt)
+            .ok()
+            .and_then(|xt| self.entries.get(xt))
+            .ok_or(EvalError::InvalidExecutionToken(xt))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interpret_keeps_state_between_lines() {
+        let mut vm = Forth::new();
+        vm.interpret(": SQUARE dup * ;").unwrap();
+        vm.interpret("7 SQUARE").unwrap();
+        assert_eq!(vm.stack(), &[49]);
+    }
+
+    #[test]
+    fn test_interpret_multi_line_definition() {
+        let mut vm = Forth::new();
+        vm.interpret(": ADD3").unwrap();
+        assert!(vm.is_pending());
+        vm.interpret("3 + ;").unwrap();
+        assert!(!vm.is_pending());
+        vm.interpret("4 ADD3").unwrap();
+        assert_eq!(vm.stack(), &[7]);
+        // Control structures and [ ] can span lines too, and STATE says
+        // compiling until the ; comes
+        vm.interpret(": SIGN? dup 0< if").unwrap();
+        vm.interpret("drop [ 2 3 + ] literal").unwrap();
+        assert_eq!(memory_ops::read_cell(&vm.memory, STATE_ADDR), Ok(-1));
+        assert!(vm.is_pending());
+        vm.interpret("then ; -1 SIGN?").unwrap();
+        assert_eq!(vm.stack(), &[7, 5]);
+        // An error drops the open definition
+        vm.interpret(": BAD 1").unwrap();
+        assert!(vm.interpret("0xZZ").is_err());
+        assert!(!vm.is_pending());
+        assert!(vm.word("BAD").is_none());
+    }
+
+    #[test]
+    fn test_define_while_definition_is_open() {
+        let mut vm = Forth::new();
+        vm.interpret(": TWICE").unwrap();
+        vm.define("DOUBLE", "2 *").unwrap();
+        assert!(vm.is_pending());
+        vm.interpret("DOUBLE DOUBLE ; 3 TWICE").unwrap();
+        assert_eq!(vm.stack(), &[12]);
+    }
+
+    #[test]
+    fn test_interpret_reports_errors() {
+        let mut vm = Forth::new();
+        assert_eq!(
+            vm.interpret("foo"),
+            Err(ForthError::Eval(EvalError::UnknownWord("foo".to_string())))
+        );
+        assert_eq!(
+            vm.interpret("1 ;"),
+            Err(ForthError::Parse(ParseError::UnexpectedToken(
+                Token::Semicolon
+            )))
+        );
+        assert!(!vm.is_pending());
+        // A malformed number stops the whole line before any of it runs
+        let mut vm = Forth::new();
+        assert_eq!(
+            vm.interpret("1 2 0xZZ"),
+            Err(ForthError::Parse(ParseError::MalformedNumber(
+                "0xZZ".to_string()
+            )))
+        );
+        assert!(vm.stack().is_empty());
+        vm.interpret("$10 #10 %10 'a' 1_000").unwrap();
+        assert_eq!(vm.stack(), &[16, 10, 2, 97, 1000]);
+        // Digits too big for a cell are the same mistake, but what fits depends
+        // on BASE, so they are only found when the parser reaches them
+        assert_eq!(
+            vm.interpret("HEX 99999999999999999"),
+            Err(ForthError::Parse(ParseError::MalformedNumber(
+                "99999999999999999".to_string()
+            )))
+        );
+        assert_eq!(vm.base(), 16);
+    }
+
+    #[test]
+    fn test_immediate_word_across_lines() {
+        let mut vm = Forth::new();
+        vm.interpret(": ANSWER 42 ;").unwrap();
+        vm.interpret("IMMEDIATE").unwrap();
+        // ANSWER runs while TEST is compiled and is not part of its body
+        vm.interpret(": TEST ANSWER 1 ;").unwrap();
+        assert_eq!(vm.stack(), &[42]);
+        assert_eq!(*vm.word("TEST").unwrap().body, [ForthOp::Push(1)]);
+    }
+
+    #[test]
+    fn test_immediate_word_same_line() {
+        let mut vm = Forth::new();
+        vm.interpret("1 : ANSWER 42 ; IMMEDIATE : TEST ANSWER ; 2")
+            .unwrap();
+        assert_eq!(vm.stack(), &[1, 42, 2]);
+        assert!(vm.word("ANSWER").unwrap().immediate);
+        assert!(vm.word("TEST").unwrap().body.is_empty());
+    }
+
+    #[test]
+    fn test_immediate_word_in_multi_line_definition_runs_once() {
+        let mut vm = Forth::new();
+        vm.interpret(": ANSWER 42 ; IMMEDIATE").unwrap();
+        vm.interpret(": TEST").unwrap();
+        vm.interpret("ANSWER").unwrap();
+        vm.interpret("dup ;").unwrap();
+        assert_eq!(vm.stack(), &[42]);
+        assert_eq!(*vm.word("TEST").unwrap().body, [ForthOp::Dup]);
+    }
+
+    #[test]
+    fn test_overflow_mode() {
+        let mut vm = Forth::new();
+        vm.interpret("9223372036854775807 1 +").unwrap();
+        assert_eq!(vm.stack(), &[i64::MIN]);
+        vm.set_overflow(Overflow::Check);
+        assert_eq!(
+            vm.interpret("-1 /"),
+            Err(ForthError::Eval(EvalError::Overflow))
+        );
+        vm.interpret("2 3 *").unwrap();
+        assert_eq!(vm.stack(), &[6]);
+    }
+
+    #[test]
+    fn test_push_pop() {
+        let mut vm = Forth::new();
+        vm.push(1);
+        vm.push(2);
+        assert_eq!(vm.pop(), Ok(2));
+        assert_eq!(vm.pop(), Ok(1));
+        assert_eq!(vm.pop(), Err(EvalError::StackUnderflow));
+    }
+
+    #[test]
+    fn test_define() {
+        let mut vm = Forth::new();
+        vm.define("double", "2 *").unwrap();
+        assert!(vm.word("DOUBLE").is_some());
+        vm.push(21);
+        vm.interpret("double").unwrap();
+        assert_eq!(vm.stack(), &[42]);
+    }
+
+    #[test]
+    fn test_register_native_word() {
+        let mut vm = Forth::new();
+        vm.register("SENSOR@", |vm| {
+            vm.push(42);
+            Ok(())
+        });
+        vm.register("clamp", |vm| {
+            let max = vm.pop()?;
+            let n = vm.pop()?;
+            vm.push(n.min(max));
+            Ok(())
+        });
+        vm.interpret(": READ sensor@ 10 clamp 1 + ;").unwrap();
+        vm.interpret("READ SENSOR@").unwrap();
+        assert_eq!(vm.stack(), &[11, 42]);
+    }
+
+    #[test]
+    fn test_register_native_word_error() {
+        let mut vm = Forth::new();
+        vm.register("FAIL", |_| Err(EvalError::DivisionByZero));
+        vm.register("NEEDS-ONE", |vm| vm.pop().map(|_| ()));
+        assert_eq!(
+            vm.interpret("FAIL").unwrap_err().eval_error(),
+            Some(&EvalError::DivisionByZero)
+        );
+        assert_eq!(
+            vm.interpret("NEEDS-ONE").unwrap_err().eval_error(),
+            Some(&EvalError::StackUnderflow)
+        );
+    }
+
+    #[test]
+    fn test_locations_follow_lines() {
+        let mut vm = Forth::new();
+        vm.interpret("\\ a comment line").unwrap();
+        vm.interpret(": BAD ( -- )").unwrap();
+        vm.interpret("  drop ;\n\n : CALLER").unwrap();
+        vm.interpret("\tBAD ;").unwrap();
+        assert_eq!(
+            vm.interpret("CALLER").unwrap_err().to_string(),
+            "Error: Stack underflow in BAD (called from CALLER at <input>:6:2, called at <input>:7:1)"
+        );
+        // A new source starts counting lines again
+        vm.set_source("lib.fth");
+        assert_eq!(
+            vm.interpret("   CALLER").unwrap_err().to_string(),
+            "Error: Stack underflow in BAD (called from CALLER at <input>:6:2, called at lib.fth:1:4)"
+        );
+        // Words defined from Rust have no locations
+        vm.define("WRAP", "CALLER").unwrap();
+        assert_eq!(
+            vm.run(&[ForthOp::Call(vm.lookup_xt("WRAP").unwrap(), None)])
+                .unwrap_err()
+                .to_string(),
+            "Error: Stack underflow in BAD (called from CALLER at <input>:6:2, called from WRAP)"
+        );
+    }
+
+    #[test]
+    fn test_finish_reports_unterminated_definition() {
+        let mut vm = Forth::new();
+        vm.interpret(": BROKEN 1 2").unwrap();
+        assert_eq!(
+            vm.finish(),
+            Err(ForthError::Parse(ParseError::UnterminatedDefinition))
+        );
+        assert!(!vm.is_pending());
+        assert_eq!(vm.finish(), Ok(()));
+    }
+}
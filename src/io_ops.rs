@@ -0,0 +1,253 @@
+use crate::eval::EvalError;
+use crate::memory_ops;
+use std::fmt;
+use std::io::{self, Read, Write};
+
+// Where KEY and ACCEPT read characters from: standard input, unless the
+// embedding program supplies another reader
+pub struct KeyInput {
+    reader: Box<dyn Read>,
+    peeked: Option<u8>, // Seen by KEY? but not yet taken by KEY
+}
+
+impl KeyInput {
+    pub fn new(reader: impl Read + 'static) -> Self {
+        KeyInput {
+            reader: Box::new(reader),
+            peeked: None,
+        }
+    }
+
+    // The next byte, or None at the end of the input
+    fn next_byte(&mut self) -> Option<u8> {
+        if let Some(byte) = self.peeked.take() {
+            return Some(byte);
+        }
+        // Output without a newline, like a prompt, must show before we wait
+        let _ = io::stdout().flush();
+        let mut byte = [0];
+        self.reader.read_exact(&mut byte).ok().map(|_| byte[0])
+    }
+
+    fn has_byte(&mut self) -> bool {
+        if self.peeked.is_none() {
+            self.peeked = self.next_byte();
+        }
+        self.peeked.is_some()
+    }
+}
+
+impl Default for KeyInput {
+    fn default() -> Self {
+        KeyInput::new(io::stdin())
+    }
+}
+
+impl fmt::Debug for KeyInput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "KeyInput")
+    }
+}
+
+// Output words write to `out`, which the interpreter points at stdout,
+// unflushed like print!. The REPL flushes after each line and KEY before it
+// waits for input. A failed write is an Io error rather than a panic.
+pub(crate) fn write_bytes(out: &mut impl Write, bytes: &[u8]) -> Result<(), EvalError> {
+    Ok(out.write_all(bytes)?)
+}
+
+// ( char -- ) Print the low byte of char
+pub fn emit(stack: &mut Vec<i64>, out: &mut impl Write) -> Result<(), EvalError> {
+    let c = stack.pop().ok_or(EvalError::StackUnderflow)?;
+    write_bytes(out, &[c as u8])
+}
+
+// ( -- )
+pub fn cr(out: &mut impl Write) -> Result<(), EvalError> {
+    write_bytes(out, b"\n")
+}
+
+// ( -- )
+pub fn space(out: &mut impl Write) -> Result<(), EvalError> {
+    write_bytes(out, b" ")
+}
+
+// The most spaces written at once: more than any line holds, but few enough
+// that a stray huge count cannot keep the interpreter writing for hours
+pub(crate) const MAX_SPACES: i64 = 1 << 16;
+
+// Write n spaces, nothing if n is not positive and MAX_SPACES if n is more.
+// They come from a small buffer; a write error ends them.
+pub(crate) fn write_spaces(out: &mut impl Write, n: i64) -> io::Result<()> {
+    const BLANKS: [u8; 64] = [b' '; 64];
+    let mut left = n.clamp(0, MAX_SPACES) as u64;
+    while left > 0 {
+        let chunk = left.min(BLANKS.len() as u64) as usize;
+        out.write_all(&BLANKS[..chunk])?;
+        left -= chunk as u64;
+    }
+    Ok(())
+}
+
+// ( n -- ) Print n spaces; nothing if n is not positive
+pub fn spaces(stack: &mut Vec<i64>, out: &mut impl Write) -> Result<(), EvalError> {
+    let n = stack.pop().ok_or(EvalError::StackUnderflow)?;
+    Ok(write_spaces(out, n)?)
+}
+
+// ( c-addr u -- )
+pub fn type_string(
+    stack: &mut Vec<i64>,
+    memory: &[u8],
+    out: &mut impl Write,
+) -> Result<(), EvalError> {
+    let len = stack.pop().ok_or(EvalError::StackUnderflow)?;
+    let addr = stack.pop().ok_or(EvalError::StackUnderflow)?;
+    write_bytes(out, memory_ops::bytes(memory, addr, len)?)
+}
+
+// ( -- char ) The next character of input, or -1 at its end
+pub fn key(stack: &mut Vec<i64>, input: &mut KeyInput) -> Result<(), EvalError> {
+    stack.push(input.next_byte().map_or(-1, i64::from));
+    Ok(())
+}
+
+// ( -- flag ) True if KEY has a character to give. On a terminal this waits
+// for a line to be typed, since input only arrives a line at a time.
+pub fn key_question(stack: &mut Vec<i64>, input: &mut KeyInput) -> Result<(), EvalError> {
+    stack.push(if input.has_byte() { -1 } else { 0 });
+    Ok(())
+}
+
+// ( c-addr +n1 -- +n2 ) Read a line into the buffer at c-addr. At most n1
+// characters are kept; the rest of the line and its newline are dropped.
+pub fn accept(
+    stack: &mut Vec<i64>,
+    memory: &mut [u8],
+    input: &mut KeyInput,
+) -> Result<(), EvalError> {
+    let max = stack.pop().ok_or(EvalError::StackUnderflow)?;
+    let addr = stack.pop().ok_or(EvalError::StackUnderflow)?;
+    let mut line = Vec::new();
+    while let Some(byte) = input.next_byte() {
+        if byte == b'\n' {
+            break;
+        }
+        line.push(byte);
+    }
+    if line.last() == Some(&b'\r') {
+        line.pop();
+    }
+    line.truncate(max.max(0) as usize);
+    memory_ops::bytes_mut(memory, addr, line.len() as i64)?.copy_from_slice(&line);
+    stack.push(line.len() as i64);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key() {
+        let mut input = KeyInput::new(&b"hi"[..]);
+        let mut s = vec![];
+        assert!(key_question(&mut s, &mut input).is_ok());
+        assert!(key(&mut s, &mut input).is_ok());
+        assert!(key(&mut s, &mut input).is_ok());
+        assert!(key_question(&mut s, &mut input).is_ok());
+        assert!(key(&mut s, &mut input).is_ok());
+        assert_eq!(s, vec![-1, 104, 105, 0, -1]);
+    }
+
+    #[test]
+    fn test_write_spaces() {
+        let mut out = Vec::new();
+        write_spaces(&mut out, 70).unwrap();
+        write_spaces(&mut out, -3).unwrap();
+        assert_eq!(out, vec![b' '; 70]);
+        // A huge count stops at MAX_SPACES
+        let mut out = Vec::new();
+        write_spaces(&mut out, i64::MAX).unwrap();
+        assert_eq!(out.len() as i64, MAX_SPACES);
+        // and a failed write ends it with the error
+        let mut full = [0u8; 100];
+        let error = write_spaces(&mut &mut full[..], 200).unwrap_err();
+        assert_eq!(full, [b' '; 100]);
+        assert_eq!(
+            EvalError::from(error),
+            EvalError::Io("failed to write whole buffer".to_string())
+        );
+    }
+
+    #[test]
+    fn test_accept() {
+        let mut input = KeyInput::new(&b"hello world\r\nab\nlast"[..]);
+        let mut memory = vec![0; 8];
+        // Only five characters fit; the rest of the line is dropped
+        let mut s = vec![1, 5];
+        assert!(accept(&mut s, &mut memory, &mut input).is_ok());
+        assert_eq!(s, vec![5]);
+        assert_eq!(&memory[1..6], b"hello");
+        let mut s = vec![0, 8];
+        assert!(accept(&mut s, &mut memory, &mut input).is_ok());
+        assert_eq!(s, vec![2]);
+        assert_eq!(&memory[..2], b"ab");
+        // The last line needs no newline
+        let mut s = vec![4, 4];
+        assert!(accept(&mut s, &mut memory, &mut input).is_ok());
+        assert_eq!(s, vec![4]);
+        let mut s = vec![0, 8];
+        assert!(accept(&mut s, &mut memory, &mut input).is_ok());
+        assert_eq!(s, vec![0]);
+        assert_eq!(memory, b"abellast");
+    }
+
+    #[test]
+    fn test_accept_errors() {
+        let mut input = KeyInput::new(&b"too long\n"[..]);
+        let mut memory = vec![0; 4];
+        assert_eq!(
+            accept(&mut vec![2, 8], &mut memory, &mut input),
+            Err(EvalError::InvalidAddress(2))
+        );
+        assert_eq!(
+            accept(&mut vec![8], &mut memory, &mut input),
+            Err(EvalError::StackUnderflow)
+        );
+    }
+
+    #[test]
+    fn test_output_stack_effects() {
+        let mut out = Vec::new();
+        let mut s = vec![b'A' as i64, -3, 2];
+        assert!(spaces(&mut s, &mut out).is_ok());
+        assert!(spaces(&mut s, &mut out).is_ok());
+        assert!(emit(&mut s, &mut out).is_ok());
+        assert!(space(&mut out).is_ok());
+        assert!(cr(&mut out).is_ok());
+        assert!(type_string(&mut vec![1, 2], b"xyz", &mut out).is_ok());
+        assert_eq!(out, b"  A \nyz");
+        assert!(s.is_empty());
+        assert_eq!(emit(&mut s, &mut out), Err(EvalError::StackUnderflow));
+        assert_eq!(spaces(&mut s, &mut out), Err(EvalError::StackUnderflow));
+        assert_eq!(
+            type_string(&mut vec![0, 2], &[], &mut out),
+            Err(EvalError::InvalidAddress(0))
+        );
+    }
+
+    #[test]
+    fn test_output_write_error() {
+        // A closed or full output is an Io error, not a panic
+        let mut full: [u8; 0] = [];
+        let error = Err(EvalError::Io("failed to write whole buffer".to_string()));
+        assert_eq!(cr(&mut &mut full[..]), error);
+        assert_eq!(space(&mut &mut full[..]), error);
+        assert_eq!(emit(&mut vec![65], &mut &mut full[..]), error);
+        assert_eq!(
+            type_string(&mut vec![0, 1], b"x", &mut &mut full[..]),
+            error
+        );
+    }
+}
@@ -0,0 +1,14 @@
+// rforth as a library: the `Forth` VM owns all interpreter state and can be
+// embedded in other tools. The `rforth` binary is a thin REPL on top of it.
+pub mod bytecode; // Flattening definitions into jumps
+pub mod eval;
+pub mod format_ops; // Number formatting and pictured output
+mod forth;
+pub mod io_ops; // Character input and output
+pub mod memory_ops; // Data space access
+pub mod number_ops; // Arithmetic and comparisons
+pub mod parser;
+pub mod stack_ops;
+pub mod token;
+
+pub use crate::forth::{DEFAULT_MAX_DEPTH, DEFAULT_SOURCE, Forth, ForthError};
@@ -1,21 +1,12 @@
-use rustyline::DefaultEditor;
+use rforth::eval::EvalError;
+use rforth::parser::ParseError;
+use rforth::{Forth, ForthError};
 use rustyline::error::ReadlineError;
-use std::collections::HashMap; // Import HashMap
+use rustyline::{Config, DefaultEditor};
 use std::fs;
-use std::io::{self, BufRead};
+use std::io::{self, Write};
 use std::path::PathBuf;
 
-mod eval;
-mod number_ops; // Declare the number_ops module for arithmetic and comparisons
-mod parser;
-mod stack_ops; // Declare the stack_ops module
-mod token;
-
-use crate::eval::{DictEntry, eval}; // Import eval function and DictEntry
-use crate::parser::{ForthOp, parse};
-use logos::Logos;
-use token::Token; // Import parse function
-
 fn get_history_path() -> Option<PathBuf> {
     home::home_dir().map(|mut path| {
         path.push(".rforth");
@@ -25,71 +16,62 @@ fn get_history_path() -> Option<PathBuf> {
 }
 
 // Function to process a line of input
-fn process_line(
-    line: &str,
-    pending_tokens: &mut Vec<Token>,
-    stack: &mut Vec<i64>,
-    dictionary: &mut HashMap<String, DictEntry>,
-    loop_control_stack: &mut Vec<(usize, i64, i64)>, // Added loop stack
-    latest_word: &mut Option<String>,                // Added latest word tracking
-) {
-    // Lex this line
-    let line_tokens: Vec<Token> = Token::lexer(line).filter_map(|r| r.ok()).collect();
-    // Append into pending buffer
-    pending_tokens.extend(line_tokens);
-    if pending_tokens.is_empty() {
-        return; // nothing to do
+fn process_line(vm: &mut Forth, line: &str) {
+    if let Err(e) = vm.interpret(line) {
+        eprintln!("{}", e);
     }
-    // Try parsing buffered tokens
-    match parse(pending_tokens.clone()) {
-        Ok(ops) => {
-            // Successfully parsed a complete definition or sequence
-            pending_tokens.clear();
-
-            // Check for Define operation to update latest_word
-            for op in &ops {
-                if let ForthOp::Define(name, _, _) = op {
-                    *latest_word = Some(name.clone());
-                }
-            }
+    // Output like `.` does not end with a newline
+    let _ = io::stdout().flush();
+}
 
-            // Pass loop_control_stack and latest_word to eval
-            if let Err(e) = eval(&ops, stack, dictionary, loop_control_stack, latest_word) {
-                eprintln!("Error: {}", e);
-                // Consider clearing loop_control_stack on error? Maybe not, depends on desired behavior.
-            }
+// Report input that ended mid-definition or with unbalanced DO/LOOP structures
+fn finish_input(vm: &mut Forth) {
+    match vm.finish() {
+        Ok(()) => {}
+        Err(ForthError::Parse(ParseError::UnterminatedDefinition)) => {
+            eprintln!("Warning: Input ended with unterminated definition or conditional.");
         }
-        Err(e) => {
-            // If still inside definition or conditional, wait for more lines
-            if matches!(
-                e,
-                crate::parser::ParseError::UnterminatedDefinition
-                    | crate::parser::ParseError::UnterminatedConditional
-            ) {
-                // Do nothing, wait for more input
-            } else {
-                // Otherwise report and clear buffer
-                eprintln!("Parse Error: {:?}", e);
-                pending_tokens.clear();
-            }
+        // A mismatch inside a word comes back Traced, so match on the error itself
+        Err(e) if e.eval_error() == Some(&EvalError::ControlStructureMismatch) => {
+            eprintln!("Warning: Input ended with unbalanced DO/LOOP structures.");
         }
+        Err(e) => eprintln!("Error processing remaining input: {}", e),
     }
 }
 
 // Use std::result::Result to avoid conflict with rustyline::Result
 fn main() -> std::result::Result<(), Box<dyn std::error::Error>> {
+    let mut vm = Forth::new(); // Owns the stack, dictionary and any open definition
+
+    // Source files given as arguments are run in order instead of a session.
+    // Errors name the file, line and column they happened at.
+    let files: Vec<String> = std::env::args().skip(1).collect();
+    if !files.is_empty() {
+        for path in &files {
+            let source = match fs::read_to_string(path) {
+                Ok(source) => source,
+                Err(e) => {
+                    eprintln!("Cannot read {}: {}", path, e);
+                    std::process::exit(1);
+                }
+            };
+            vm.set_source(path);
+            for line in source.lines() {
+                process_line(&mut vm, line);
+            }
+            finish_input(&mut vm);
+        }
+        return Ok(());
+    }
+
     println!("welcome to rforth");
 
     let history_path = get_history_path();
-    let mut stack: Vec<i64> = Vec::new(); // The Forth stack
-    let mut dictionary: HashMap<String, DictEntry> = HashMap::new(); // Create the dictionary
-    let mut loop_control_stack: Vec<(usize, i64, i64)> = Vec::new(); // Initialize loop stack
-    let mut latest_word: Option<String> = None; // Initialize latest word tracking
-
-    let mut pending_tokens = Vec::new(); // Buffer for multi-line definitions
 
     if atty::is(atty::Stream::Stdin) {
-        let mut rl = DefaultEditor::new()?;
+        // Start the prompt on a new line when the output did not end one
+        let config = Config::builder().check_cursor_position(true).build();
+        let mut rl = DefaultEditor::with_config(config)?;
 
         if let Some(ref path) = history_path {
             // Create the directory if it doesn't exist
@@ -109,15 +91,7 @@ fn main() -> std::result::Result<(), Box<dyn std::error::Error>> {
                 Ok(line) => {
                     // Add line to history before processing
                     let _ = rl.add_history_entry(line.as_str());
-                    // Pass loop_control_stack and latest_word to process_line
-                    process_line(
-                        &line,
-                        &mut pending_tokens,
-                        &mut stack,
-                        &mut dictionary,
-                        &mut loop_control_stack,
-                        &mut latest_word,
-                    );
+                    process_line(&mut vm, &line);
                 }
                 Err(ReadlineError::Interrupted) => {
                     println!("CTRL-C");
@@ -133,73 +107,28 @@ fn main() -> std::result::Result<(), Box<dyn std::error::Error>> {
                 }
             }
         }
-        if let Some(ref path) = history_path {
-            if let Err(err) = rl.save_history(path) {
-                eprintln!("Failed to save history to {:?}: {}", path, err);
-            }
+        if let Some(ref path) = history_path
+            && let Err(err) = rl.save_history(path)
+        {
+            eprintln!("Failed to save history to {:?}: {}", path, err);
         }
     } else {
-        // Piped input
+        // Piped input. Stdin is only locked while a line is read, so KEY and
+        // ACCEPT can take the lines that follow.
         let stdin = io::stdin();
-        for line in stdin.lock().lines() {
-            match line {
-                Ok(l) => {
-                    // Pass loop_control_stack and latest_word to process_line
-                    process_line(
-                        &l,
-                        &mut pending_tokens,
-                        &mut stack,
-                        &mut dictionary,
-                        &mut loop_control_stack,
-                        &mut latest_word,
-                    );
-                }
+        let mut line = String::new();
+        loop {
+            line.clear();
+            match stdin.read_line(&mut line) {
+                Ok(0) => break,
+                Ok(_) => process_line(&mut vm, line.trim_end_matches(['\n', '\r'])),
                 Err(e) => {
                     eprintln!("Error reading stdin: {}", e);
                     break;
                 }
             }
         }
-        // After processing all lines from stdin, check if there's anything left in pending_tokens
-        // This might happen if the input ends mid-definition or conditional.
-        // We could choose to error, warn, or attempt final processing.
-        // For now, let's just clear it if it's an unterminated state, otherwise try one last parse/eval.
-        if !pending_tokens.is_empty() {
-            match parse(pending_tokens.clone()) {
-                Ok(ops) => {
-                    // Pass loop_control_stack and latest_word to final eval
-                    if let Err(e) = eval(
-                        &ops,
-                        &mut stack,
-                        &mut dictionary,
-                        &mut loop_control_stack,
-                        &mut latest_word,
-                    ) {
-                        eprintln!("Error processing remaining input: {}", e);
-                    }
-                }
-                Err(e) => {
-                    if !matches!(
-                        e,
-                        crate::parser::ParseError::UnterminatedDefinition
-                            | crate::parser::ParseError::UnterminatedConditional
-                    ) {
-                        eprintln!("Parse Error processing remaining input: {:?}", e);
-                    } else {
-                        eprintln!(
-                            "Warning: Input ended with unterminated definition or conditional."
-                        );
-                    }
-                }
-            }
-            pending_tokens.clear(); // Clear buffer regardless
-        }
-        // Check if loop stack is non-empty at the end (indicates unterminated loop in piped input)
-        if !loop_control_stack.is_empty() {
-            eprintln!("Warning: Input ended with unbalanced DO/LOOP structures.");
-            // Optionally clear the loop stack here if desired
-            // loop_control_stack.clear();
-        }
+        finish_input(&mut vm);
     }
 
     Ok(())
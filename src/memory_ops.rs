@@ -0,0 +1,433 @@
+use crate::eval::EvalError;
+use crate::number_ops::Overflow;
+
+// Size of a cell in bytes: cells hold one i64
+pub const CELL_SIZE: i64 = 8;
+
+// Upper bound on the data space, so a runaway ALLOT fails instead of
+// exhausting the host's memory
+pub const MAX_MEMORY: usize = 16 * 1024 * 1024;
+
+// Check that `len` bytes starting at `addr` lie inside the data space and
+// return the start as an index
+fn check_range(memory: &[u8], addr: i64, len: usize) -> Result<usize, EvalError> {
+    match usize::try_from(addr) {
+        Ok(start)
+            if start
+                .checked_add(len)
+                .is_some_and(|end| end <= memory.len()) =>
+        {
+            Ok(start)
+        }
+        _ => Err(EvalError::InvalidAddress(addr)),
+    }
+}
+
+pub fn read_cell(memory: &[u8], addr: i64) -> Result<i64, EvalError> {
+    let start = check_range(memory, addr, CELL_SIZE as usize)?;
+    let bytes = memory[start..start + CELL_SIZE as usize]
+        .try_into()
+        .unwrap();
+    Ok(i64::from_le_bytes(bytes))
+}
+
+pub fn write_cell(memory: &mut [u8], addr: i64, value: i64) -> Result<(), EvalError> {
+    let start = check_range(memory, addr, CELL_SIZE as usize)?;
+    memory[start..start + CELL_SIZE as usize].copy_from_slice(&value.to_le_bytes());
+    Ok(())
+}
+
+// Round an address up to the next cell boundary
+fn align_up(addr: i64) -> i64 {
+    addr.wrapping_add(CELL_SIZE - 1) & !(CELL_SIZE - 1)
+}
+
+// ( -- addr ) The next free byte of the data space
+pub fn here(stack: &mut Vec<i64>, memory: &[u8]) -> Result<(), EvalError> {
+    stack.push(memory.len() as i64);
+    Ok(())
+}
+
+// ( n -- ) Reserve n bytes of zeroed data space; a negative n releases space
+pub fn allot(stack: &mut Vec<i64>, memory: &mut Vec<u8>) -> Result<(), EvalError> {
+    let n = stack.pop().ok_or(EvalError::StackUnderflow)?;
+    allot_bytes(memory, n)
+}
+
+// ALLOT for callers that have n in hand rather than on the stack
+pub fn allot_bytes(memory: &mut Vec<u8>, n: i64) -> Result<(), EvalError> {
+    let new_here = (memory.len() as i64).wrapping_add(n);
+    match usize::try_from(new_here) {
+        Ok(len) if len <= MAX_MEMORY => {
+            memory.resize(len, 0);
+            Ok(())
+        }
+        _ => Err(EvalError::InvalidAddress(new_here)),
+    }
+}
+
+// ( x -- ) Append a cell to the data space
+pub fn comma(stack: &mut Vec<i64>, memory: &mut Vec<u8>) -> Result<(), EvalError> {
+    let x = stack.pop().ok_or(EvalError::StackUnderflow)?;
+    if memory.len() + CELL_SIZE as usize > MAX_MEMORY {
+        return Err(EvalError::InvalidAddress(memory.len() as i64));
+    }
+    memory.extend_from_slice(&x.to_le_bytes());
+    Ok(())
+}
+
+// ( char -- ) Append a byte to the data space
+pub fn c_comma(stack: &mut Vec<i64>, memory: &mut Vec<u8>) -> Result<(), EvalError> {
+    let c = stack.pop().ok_or(EvalError::StackUnderflow)?;
+    if memory.len() >= MAX_MEMORY {
+        return Err(EvalError::InvalidAddress(memory.len() as i64));
+    }
+    memory.push(c as u8);
+    Ok(())
+}
+
+// ( addr -- x )
+pub fn fetch(stack: &mut Vec<i64>, memory: &[u8]) -> Result<(), EvalError> {
+    let addr = stack.pop().ok_or(EvalError::StackUnderflow)?;
+    stack.push(read_cell(memory, addr)?);
+    Ok(())
+}
+
+// ( x addr -- )
+pub fn store(stack: &mut Vec<i64>, memory: &mut [u8]) -> Result<(), EvalError> {
+    let addr = stack.pop().ok_or(EvalError::StackUnderflow)?;
+    let x = stack.pop().ok_or(EvalError::StackUnderflow)?;
+    write_cell(memory, addr, x)
+}
+
+// ( addr -- x1 x2 ) x2 is the cell at addr and x1 the one after it
+pub fn two_fetch(stack: &mut Vec<i64>, memory: &[u8]) -> Result<(), EvalError> {
+    let addr = stack.pop().ok_or(EvalError::StackUnderflow)?;
+    let next = addr
+        .checked_add(CELL_SIZE)
+        .ok_or(EvalError::InvalidAddress(addr))?;
+    let x2 = read_cell(memory, addr)?;
+    stack.push(read_cell(memory, next)?);
+    stack.push(x2);
+    Ok(())
+}
+
+// ( x1 x2 addr -- ) Stores x2 at addr and x1 in the cell after it
+pub fn two_store(stack: &mut Vec<i64>, memory: &mut [u8]) -> Result<(), EvalError> {
+    let addr = stack.pop().ok_or(EvalError::StackUnderflow)?;
+    let x2 = stack.pop().ok_or(EvalError::StackUnderflow)?;
+    let x1 = stack.pop().ok_or(EvalError::StackUnderflow)?;
+    let next = addr
+        .checked_add(CELL_SIZE)
+        .ok_or(EvalError::InvalidAddress(addr))?;
+    // Check both cells before writing either
+    read_cell(memory, next)?;
+    write_cell(memory, addr, x2)?;
+    write_cell(memory, next, x1)
+}
+
+// ( addr -- char )
+pub fn c_fetch(stack: &mut Vec<i64>, memory: &[u8]) -> Result<(), EvalError> {
+    let addr = stack.pop().ok_or(EvalError::StackUnderflow)?;
+    let start = check_range(memory, addr, 1)?;
+    stack.push(memory[start] as i64);
+    Ok(())
+}
+
+// ( char addr -- ) Stores the low byte of char
+pub fn c_store(stack: &mut Vec<i64>, memory: &mut [u8]) -> Result<(), EvalError> {
+    let addr = stack.pop().ok_or(EvalError::StackUnderflow)?;
+    let c = stack.pop().ok_or(EvalError::StackUnderflow)?;
+    let start = check_range(memory, addr, 1)?;
+    memory[start] = c as u8;
+    Ok(())
+}
+
+// ( n addr -- ) Add n to the cell at addr
+pub fn plus_store(
+    stack: &mut Vec<i64>,
+    memory: &mut [u8],
+    overflow: Overflow,
+) -> Result<(), EvalError> {
+    let addr = stack.pop().ok_or(EvalError::StackUnderflow)?;
+    let n = stack.pop().ok_or(EvalError::StackUnderflow)?;
+    let x = read_cell(memory, addr)?;
+    write_cell(memory, addr, overflow.check(x.overflowing_add(n))?)
+}
+
+// ( n -- n*cell )
+pub fn cells(stack: &mut Vec<i64>, overflow: Overflow) -> Result<(), EvalError> {
+    let n = stack.pop().ok_or(EvalError::StackUnderflow)?;
+    stack.push(overflow.check(n.overflowing_mul(CELL_SIZE))?);
+    Ok(())
+}
+
+// ( addr -- addr+cell )
+pub fn cell_plus(stack: &mut Vec<i64>, overflow: Overflow) -> Result<(), EvalError> {
+    let addr = stack.pop().ok_or(EvalError::StackUnderflow)?;
+    stack.push(overflow.check(addr.overflowing_add(CELL_SIZE))?);
+    Ok(())
+}
+
+// ( n -- n ) Characters are one byte, so this only checks the stack
+pub fn chars(stack: &mut [i64]) -> Result<(), EvalError> {
+    if stack.is_empty() {
+        return Err(EvalError::StackUnderflow);
+    }
+    Ok(())
+}
+
+// ( -- ) Pad the data space up to a cell boundary
+pub fn align(memory: &mut Vec<u8>) -> Result<(), EvalError> {
+    let aligned = align_up(memory.len() as i64) as usize;
+    if aligned > MAX_MEMORY {
+        return Err(EvalError::InvalidAddress(aligned as i64));
+    }
+    memory.resize(aligned, 0);
+    Ok(())
+}
+
+// ( addr -- a-addr ) Round addr up to a cell boundary
+pub fn aligned(stack: &mut Vec<i64>) -> Result<(), EvalError> {
+    let addr = stack.pop().ok_or(EvalError::StackUnderflow)?;
+    stack.push(align_up(addr));
+    Ok(())
+}
+
+// ( c-addr -- c-addr+1 u ) The text and length of a counted string
+pub fn count(stack: &mut Vec<i64>, memory: &[u8]) -> Result<(), EvalError> {
+    let addr = stack.pop().ok_or(EvalError::StackUnderflow)?;
+    let start = check_range(memory, addr, 1)?;
+    stack.push(addr + 1);
+    stack.push(memory[start] as i64);
+    Ok(())
+}
+
+// The `len` bytes starting at `addr`, as given by a ( c-addr u ) pair
+pub fn bytes(memory: &[u8], addr: i64, len: i64) -> Result<&[u8], EvalError> {
+    let len = usize::try_from(len).map_err(|_| EvalError::InvalidAddress(addr))?;
+    let start = check_range(memory, addr, len)?;
+    Ok(&memory[start..start + len])
+}
+
+pub fn bytes_mut(memory: &mut [u8], addr: i64, len: i64) -> Result<&mut [u8], EvalError> {
+    let len = usize::try_from(len).map_err(|_| EvalError::InvalidAddress(addr))?;
+    let start = check_range(memory, addr, len)?;
+    Ok(&mut memory[start..start + len])
+}
+
+// Append bytes to the data space and return their address
+pub fn store_bytes(memory: &mut Vec<u8>, bytes: &[u8]) -> Result<i64, EvalError> {
+    let addr = memory.len();
+    if addr + bytes.len() > MAX_MEMORY {
+        return Err(EvalError::InvalidAddress(addr as i64));
+    }
+    memory.extend_from_slice(bytes);
+    Ok(addr as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_here_allot() {
+        let mut stack = vec![];
+        let mut memory = vec![];
+        assert!(here(&mut stack, &memory).is_ok());
+        assert_eq!(stack, vec![0]);
+        stack.push(10);
+        assert!(allot(&mut stack, &mut memory).is_ok());
+        assert_eq!(memory, vec![0; 10]);
+        // Negative ALLOT gives space back
+        let mut s = vec![-4];
+        assert!(allot(&mut s, &mut memory).is_ok());
+        assert_eq!(memory.len(), 6);
+        assert_eq!(
+            allot(&mut vec![-7], &mut memory),
+            Err(EvalError::InvalidAddress(-1))
+        );
+        assert_eq!(
+            allot(&mut vec![MAX_MEMORY as i64], &mut memory),
+            Err(EvalError::InvalidAddress(MAX_MEMORY as i64 + 6))
+        );
+        assert_eq!(memory.len(), 6);
+        assert_eq!(
+            allot(&mut vec![], &mut memory),
+            Err(EvalError::StackUnderflow)
+        );
+        assert!(allot_bytes(&mut memory, 2).is_ok());
+        assert_eq!(memory.len(), 8);
+        assert_eq!(
+            allot_bytes(&mut memory, -9),
+            Err(EvalError::InvalidAddress(-1))
+        );
+    }
+
+    #[test]
+    fn test_comma() {
+        let mut memory = vec![];
+        assert!(c_comma(&mut vec![0x141], &mut memory).is_ok());
+        assert!(comma(&mut vec![-2], &mut memory).is_ok());
+        assert_eq!(memory.len(), 9);
+        assert_eq!(memory[0], 0x41);
+        let mut s = vec![1];
+        assert!(fetch(&mut s, &memory).is_ok());
+        assert_eq!(s, vec![-2]);
+    }
+
+    #[test]
+    fn test_fetch_store() {
+        let mut memory = vec![0; 16];
+        let mut s = vec![123456789, 8];
+        assert!(store(&mut s, &mut memory).is_ok());
+        assert!(s.is_empty());
+        let mut t = vec![8];
+        assert!(fetch(&mut t, &memory).is_ok());
+        assert_eq!(t, vec![123456789]);
+        let mut u = vec![5, 8];
+        assert!(plus_store(&mut u, &mut memory, Overflow::Wrap).is_ok());
+        let mut v = vec![8];
+        assert!(fetch(&mut v, &memory).is_ok());
+        assert_eq!(v, vec![123456794]);
+    }
+
+    #[test]
+    fn test_two_fetch_two_store() {
+        let mut memory = vec![0; 24];
+        let mut s = vec![1, 2, 8];
+        assert!(two_store(&mut s, &mut memory).is_ok());
+        assert!(s.is_empty());
+        assert_eq!(read_cell(&memory, 8), Ok(2));
+        assert_eq!(read_cell(&memory, 16), Ok(1));
+        let mut t = vec![8];
+        assert!(two_fetch(&mut t, &memory).is_ok());
+        assert_eq!(t, vec![1, 2]);
+        // Neither cell is written unless both fit
+        assert_eq!(
+            two_store(&mut vec![3, 4, 12], &mut memory),
+            Err(EvalError::InvalidAddress(20))
+        );
+        assert_eq!(read_cell(&memory, 8), Ok(2));
+        assert_eq!(
+            two_fetch(&mut vec![i64::MAX], &memory),
+            Err(EvalError::InvalidAddress(i64::MAX))
+        );
+        assert_eq!(
+            two_store(&mut vec![1, 8], &mut memory),
+            Err(EvalError::StackUnderflow)
+        );
+    }
+
+    #[test]
+    fn test_c_fetch_c_store() {
+        let mut memory = vec![0; 4];
+        let mut s = vec![0x1FF, 3];
+        assert!(c_store(&mut s, &mut memory).is_ok());
+        assert_eq!(memory, vec![0, 0, 0, 0xFF]);
+        let mut t = vec![3];
+        assert!(c_fetch(&mut t, &memory).is_ok());
+        assert_eq!(t, vec![255]);
+    }
+
+    #[test]
+    fn test_invalid_address() {
+        let mut memory = vec![0; 10];
+        // A cell must fit entirely inside the data space
+        assert_eq!(
+            fetch(&mut vec![3], &memory),
+            Err(EvalError::InvalidAddress(3))
+        );
+        assert_eq!(
+            fetch(&mut vec![-8], &memory),
+            Err(EvalError::InvalidAddress(-8))
+        );
+        assert_eq!(
+            fetch(&mut vec![i64::MAX], &memory),
+            Err(EvalError::InvalidAddress(i64::MAX))
+        );
+        assert_eq!(
+            store(&mut vec![1, 4], &mut memory),
+            Err(EvalError::InvalidAddress(4))
+        );
+        assert_eq!(
+            c_fetch(&mut vec![10], &memory),
+            Err(EvalError::InvalidAddress(10))
+        );
+        assert_eq!(
+            c_store(&mut vec![1, -1], &mut memory),
+            Err(EvalError::InvalidAddress(-1))
+        );
+        assert_eq!(
+            plus_store(&mut vec![1, 9], &mut memory, Overflow::Wrap),
+            Err(EvalError::InvalidAddress(9))
+        );
+        assert_eq!(
+            store(&mut vec![1], &mut memory),
+            Err(EvalError::StackUnderflow)
+        );
+    }
+
+    #[test]
+    fn test_cells_and_alignment() {
+        let mut s = vec![3];
+        assert!(cells(&mut s, Overflow::Wrap).is_ok());
+        assert_eq!(s, vec![24]);
+        assert!(cell_plus(&mut s, Overflow::Wrap).is_ok());
+        assert_eq!(s, vec![32]);
+        assert!(chars(&mut s).is_ok());
+        assert_eq!(s, vec![32]);
+        assert_eq!(chars(&mut []), Err(EvalError::StackUnderflow));
+        let mut a = vec![9];
+        assert!(aligned(&mut a).is_ok());
+        assert_eq!(a, vec![16]);
+        let mut b = vec![16];
+        assert!(aligned(&mut b).is_ok());
+        assert_eq!(b, vec![16]);
+        let mut memory = vec![1, 2, 3];
+        assert!(align(&mut memory).is_ok());
+        assert_eq!(memory, vec![1, 2, 3, 0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_cell_arithmetic_overflow() {
+        let mut memory = vec![0; 8];
+        assert!(write_cell(&mut memory, 0, i64::MAX).is_ok());
+        assert!(plus_store(&mut vec![1, 0], &mut memory, Overflow::Wrap).is_ok());
+        assert_eq!(read_cell(&memory, 0), Ok(i64::MIN));
+        let mut s = vec![i64::MAX];
+        assert!(cell_plus(&mut s, Overflow::Wrap).is_ok());
+        assert_eq!(s, vec![i64::MIN + 7]);
+        // Checked mode reports them like the arithmetic words do
+        assert_eq!(
+            plus_store(&mut vec![-1, 0], &mut memory, Overflow::Check),
+            Err(EvalError::Overflow)
+        );
+        assert_eq!(read_cell(&memory, 0), Ok(i64::MIN));
+        assert_eq!(
+            cells(&mut vec![i64::MAX / 4], Overflow::Check),
+            Err(EvalError::Overflow)
+        );
+        assert_eq!(
+            cell_plus(&mut vec![i64::MAX], Overflow::Check),
+            Err(EvalError::Overflow)
+        );
+    }
+
+    #[test]
+    fn test_strings() {
+        let mut memory = vec![0; 3];
+        assert_eq!(store_bytes(&mut memory, b"\x02hi"), Ok(3));
+        let mut s = vec![3];
+        assert!(count(&mut s, &memory).is_ok());
+        assert_eq!(s, vec![4, 2]);
+        assert_eq!(bytes(&memory, 4, 2), Ok(&b"hi"[..]));
+        assert_eq!(bytes(&memory, 4, 0), Ok(&b""[..]));
+        assert_eq!(bytes(&memory, 4, 3), Err(EvalError::InvalidAddress(4)));
+        assert_eq!(bytes(&memory, 4, -1), Err(EvalError::InvalidAddress(4)));
+        assert_eq!(
+            count(&mut vec![6], &memory),
+            Err(EvalError::InvalidAddress(6))
+        );
+    }
+}
@@ -1,48 +1,269 @@
 use crate::eval::EvalError;
 
-// Arithmetic operations
-pub fn add(stack: &mut Vec<i64>) -> Result<(), EvalError> {
-    let b = stack.pop().ok_or(EvalError::StackUnderflow)?;
+// What arithmetic does with a result too big for a cell. Forth expects
+// two's complement wrap around, so 9223372036854775807 1 + gives the most
+// negative number; checked mode reports an error instead.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Overflow {
+    #[default]
+    Wrap, // Keep the low 64 bits of the result
+    Check, // Fail with EvalError::Overflow
+}
+
+impl Overflow {
+    // A result as given by i64's overflowing_* methods: the wrapped value and
+    // whether it overflowed
+    pub(crate) fn check(self, (value, overflowed): (i64, bool)) -> Result<i64, EvalError> {
+        if overflowed && self == Overflow::Check {
+            return Err(EvalError::Overflow);
+        }
+        Ok(value)
+    }
+
+    // A double-width result that has to fit back in a cell
+    fn narrow(self, value: i128) -> Result<i64, EvalError> {
+        self.check((value as i64, i64::try_from(value).is_err()))
+    }
+}
+
+// Helpers for the many words that just map their inputs to one result
+fn unary(stack: &mut Vec<i64>, f: impl Fn(i64) -> i64) -> Result<(), EvalError> {
     let a = stack.pop().ok_or(EvalError::StackUnderflow)?;
-    stack.push(a + b);
+    stack.push(f(a));
     Ok(())
 }
 
-pub fn subtract(stack: &mut Vec<i64>) -> Result<(), EvalError> {
+fn binary(stack: &mut Vec<i64>, f: impl Fn(i64, i64) -> i64) -> Result<(), EvalError> {
     let b = stack.pop().ok_or(EvalError::StackUnderflow)?;
     let a = stack.pop().ok_or(EvalError::StackUnderflow)?;
-    stack.push(a - b);
+    stack.push(f(a, b));
     Ok(())
 }
 
-pub fn multiply(stack: &mut Vec<i64>) -> Result<(), EvalError> {
+// The same for arithmetic that can overflow
+fn unary_arithmetic(
+    stack: &mut Vec<i64>,
+    overflow: Overflow,
+    f: impl Fn(i64) -> (i64, bool),
+) -> Result<(), EvalError> {
+    let a = stack.pop().ok_or(EvalError::StackUnderflow)?;
+    stack.push(overflow.check(f(a))?);
+    Ok(())
+}
+
+fn binary_arithmetic(
+    stack: &mut Vec<i64>,
+    overflow: Overflow,
+    f: impl Fn(i64, i64) -> (i64, bool),
+) -> Result<(), EvalError> {
     let b = stack.pop().ok_or(EvalError::StackUnderflow)?;
     let a = stack.pop().ok_or(EvalError::StackUnderflow)?;
-    stack.push(a * b);
+    stack.push(overflow.check(f(a, b))?);
     Ok(())
 }
 
-pub fn divide(stack: &mut Vec<i64>) -> Result<(), EvalError> {
+fn flag(condition: bool) -> i64 {
+    if condition { -1 } else { 0 }
+}
+
+// Arithmetic operations
+pub fn add(stack: &mut Vec<i64>, overflow: Overflow) -> Result<(), EvalError> {
+    binary_arithmetic(stack, overflow, i64::overflowing_add)
+}
+
+pub fn subtract(stack: &mut Vec<i64>, overflow: Overflow) -> Result<(), EvalError> {
+    binary_arithmetic(stack, overflow, i64::overflowing_sub)
+}
+
+pub fn multiply(stack: &mut Vec<i64>, overflow: Overflow) -> Result<(), EvalError> {
+    binary_arithmetic(stack, overflow, i64::overflowing_mul)
+}
+
+// The only quotient that overflows is the most negative number divided by -1
+pub fn divide(stack: &mut Vec<i64>, overflow: Overflow) -> Result<(), EvalError> {
     let b = stack.pop().ok_or(EvalError::StackUnderflow)?;
     let a = stack.pop().ok_or(EvalError::StackUnderflow)?;
     if b == 0 {
         return Err(EvalError::DivisionByZero);
     }
-    stack.push(a / b);
+    stack.push(overflow.check(a.overflowing_div(b))?);
     Ok(())
 }
 
-// ( a b -- a % b )
+// ( a b -- a % b ) A remainder always fits, even for the most negative number
+// divided by -1, where it is 0
 pub fn mod_op(stack: &mut Vec<i64>) -> Result<(), EvalError> {
     let b = stack.pop().ok_or(EvalError::StackUnderflow)?;
     let a = stack.pop().ok_or(EvalError::StackUnderflow)?;
     if b == 0 {
         return Err(EvalError::DivisionByZero);
     }
-    stack.push(a % b);
+    stack.push(a.wrapping_rem(b));
+    Ok(())
+}
+
+pub fn one_plus(stack: &mut Vec<i64>, overflow: Overflow) -> Result<(), EvalError> {
+    unary_arithmetic(stack, overflow, |a| a.overflowing_add(1))
+}
+
+pub fn one_minus(stack: &mut Vec<i64>, overflow: Overflow) -> Result<(), EvalError> {
+    unary_arithmetic(stack, overflow, |a| a.overflowing_sub(1))
+}
+
+pub fn negate(stack: &mut Vec<i64>, overflow: Overflow) -> Result<(), EvalError> {
+    unary_arithmetic(stack, overflow, i64::overflowing_neg)
+}
+
+pub fn abs(stack: &mut Vec<i64>, overflow: Overflow) -> Result<(), EvalError> {
+    unary_arithmetic(stack, overflow, i64::overflowing_abs)
+}
+
+pub fn min(stack: &mut Vec<i64>) -> Result<(), EvalError> {
+    binary(stack, |a, b| a.min(b))
+}
+
+pub fn max(stack: &mut Vec<i64>) -> Result<(), EvalError> {
+    binary(stack, |a, b| a.max(b))
+}
+
+// Division with a remainder. Symmetric division, like / and MOD, rounds the
+// quotient toward zero, so the remainder has the dividend's sign. Floored
+// division rounds toward negative infinity, so the remainder has the
+// divisor's sign: -7 2 gives -1 -3 symmetric but 1 -4 floored.
+// The dividend may be a double cell, so the sums are done in 128 bits. The
+// remainder always fits in a cell; the quotient may not.
+
+// ( rem quot ) of n / d, rounding toward zero
+fn symmetric(n: i128, d: i128, overflow: Overflow) -> Result<(i64, i64), EvalError> {
+    if d == 0 {
+        return Err(EvalError::DivisionByZero);
+    }
+    let quot = overflow.narrow(n.wrapping_div(d))?;
+    Ok((n.wrapping_rem(d) as i64, quot))
+}
+
+// ( rem quot ) of n / d, rounding toward negative infinity
+fn floored(n: i128, d: i128, overflow: Overflow) -> Result<(i64, i64), EvalError> {
+    if d == 0 {
+        return Err(EvalError::DivisionByZero);
+    }
+    let (mut rem, mut quot) = (n.wrapping_rem(d), n.wrapping_div(d));
+    // Rounding toward zero went up instead of down when the signs differ
+    if rem != 0 && (rem < 0) != (d < 0) {
+        rem += d;
+        quot -= 1;
+    }
+    Ok((rem as i64, overflow.narrow(quot)?))
+}
+
+// A double-cell number from the stack: low cell below, high cell on top
+fn pop_double(stack: &mut Vec<i64>) -> Result<i128, EvalError> {
+    let hi = stack.pop().ok_or(EvalError::StackUnderflow)?;
+    let lo = stack.pop().ok_or(EvalError::StackUnderflow)?;
+    Ok(((hi as i128) << 64) | lo as u64 as i128)
+}
+
+// ( n1 n2 -- rem quot )
+pub fn slash_mod(stack: &mut Vec<i64>, overflow: Overflow) -> Result<(), EvalError> {
+    let d = stack.pop().ok_or(EvalError::StackUnderflow)?;
+    let n = stack.pop().ok_or(EvalError::StackUnderflow)?;
+    let (rem, quot) = symmetric(n as i128, d as i128, overflow)?;
+    stack.extend([rem, quot]);
+    Ok(())
+}
+
+// ( n1 n2 n3 -- quot ) n1*n2/n3, with a product that cannot overflow
+pub fn star_slash(stack: &mut Vec<i64>, overflow: Overflow) -> Result<(), EvalError> {
+    star_slash_mod(stack, overflow)?;
+    let quot = stack.pop().ok_or(EvalError::StackUnderflow)?;
+    stack.pop();
+    stack.push(quot);
+    Ok(())
+}
+
+// ( n1 n2 n3 -- rem quot )
+pub fn star_slash_mod(stack: &mut Vec<i64>, overflow: Overflow) -> Result<(), EvalError> {
+    let d = stack.pop().ok_or(EvalError::StackUnderflow)?;
+    let b = stack.pop().ok_or(EvalError::StackUnderflow)?;
+    let a = stack.pop().ok_or(EvalError::StackUnderflow)?;
+    let (rem, quot) = symmetric(a as i128 * b as i128, d as i128, overflow)?;
+    stack.extend([rem, quot]);
+    Ok(())
+}
+
+// ( d n -- rem quot ) Floored division of a double-cell number
+pub fn fm_slash_mod(stack: &mut Vec<i64>, overflow: Overflow) -> Result<(), EvalError> {
+    let d = stack.pop().ok_or(EvalError::StackUnderflow)?;
+    let n = pop_double(stack)?;
+    let (rem, quot) = floored(n, d as i128, overflow)?;
+    stack.extend([rem, quot]);
+    Ok(())
+}
+
+// ( d n -- rem quot ) Symmetric division of a double-cell number
+pub fn sm_slash_rem(stack: &mut Vec<i64>, overflow: Overflow) -> Result<(), EvalError> {
+    let d = stack.pop().ok_or(EvalError::StackUnderflow)?;
+    let n = pop_double(stack)?;
+    let (rem, quot) = symmetric(n, d as i128, overflow)?;
+    stack.extend([rem, quot]);
     Ok(())
 }
 
+// Bitwise operations
+pub fn and(stack: &mut Vec<i64>) -> Result<(), EvalError> {
+    binary(stack, |a, b| a & b)
+}
+
+pub fn or(stack: &mut Vec<i64>) -> Result<(), EvalError> {
+    binary(stack, |a, b| a | b)
+}
+
+pub fn xor(stack: &mut Vec<i64>) -> Result<(), EvalError> {
+    binary(stack, |a, b| a ^ b)
+}
+
+// ( x -- ~x )
+pub fn invert(stack: &mut Vec<i64>) -> Result<(), EvalError> {
+    unary(stack, |a| !a)
+}
+
+// Shifts take the count as unsigned. Shifting by the cell width or more moves
+// every bit out instead of panicking, so a negative count does too.
+
+// ( x u -- x<<u )
+pub fn lshift(stack: &mut Vec<i64>) -> Result<(), EvalError> {
+    binary(stack, |a, u| {
+        a.checked_shl(u.try_into().unwrap_or(u32::MAX)).unwrap_or(0)
+    })
+}
+
+// ( x u -- x>>u ) Logical: zeros shift in from the top
+pub fn rshift(stack: &mut Vec<i64>) -> Result<(), EvalError> {
+    binary(stack, |a, u| {
+        let shifted = (a as u64).checked_shr(u.try_into().unwrap_or(u32::MAX));
+        shifted.unwrap_or(0) as i64
+    })
+}
+
+// ( n u -- n>>u ) Arithmetic: copies of the sign bit shift in
+pub fn arshift(stack: &mut Vec<i64>) -> Result<(), EvalError> {
+    binary(stack, |a, u| {
+        let shifted = a.checked_shr(u.try_into().unwrap_or(u32::MAX));
+        shifted.unwrap_or(if a < 0 { -1 } else { 0 })
+    })
+}
+
+// ( x -- x<<1 ) Shifting out a bit that differs from the new sign bit is an
+// overflow, as for 2 *
+pub fn two_star(stack: &mut Vec<i64>, overflow: Overflow) -> Result<(), EvalError> {
+    unary_arithmetic(stack, overflow, |a| a.overflowing_mul(2))
+}
+
+// ( n -- n>>1 ) Arithmetic shift, so -1 2/ is -1
+pub fn two_slash(stack: &mut Vec<i64>) -> Result<(), EvalError> {
+    unary(stack, |a| a >> 1)
+}
+
 // Comparison operations: push 1 for true, 0 for false
 pub fn eq(stack: &mut Vec<i64>) -> Result<(), EvalError> {
     let b = stack.pop().ok_or(EvalError::StackUnderflow)?;
@@ -68,6 +289,56 @@ pub fn gt(stack: &mut Vec<i64>) -> Result<(), EvalError> {
     Ok(())
 }
 
+pub fn ne(stack: &mut Vec<i64>) -> Result<(), EvalError> {
+    binary(stack, |a, b| flag(a != b))
+}
+
+pub fn le(stack: &mut Vec<i64>) -> Result<(), EvalError> {
+    binary(stack, |a, b| flag(a <= b))
+}
+
+pub fn ge(stack: &mut Vec<i64>) -> Result<(), EvalError> {
+    binary(stack, |a, b| flag(a >= b))
+}
+
+pub fn zero_eq(stack: &mut Vec<i64>) -> Result<(), EvalError> {
+    unary(stack, |a| flag(a == 0))
+}
+
+pub fn zero_ne(stack: &mut Vec<i64>) -> Result<(), EvalError> {
+    unary(stack, |a| flag(a != 0))
+}
+
+pub fn zero_lt(stack: &mut Vec<i64>) -> Result<(), EvalError> {
+    unary(stack, |a| flag(a < 0))
+}
+
+pub fn zero_gt(stack: &mut Vec<i64>) -> Result<(), EvalError> {
+    unary(stack, |a| flag(a > 0))
+}
+
+// U< and U> compare the cells as unsigned numbers, so -1 is the largest
+pub fn u_lt(stack: &mut Vec<i64>) -> Result<(), EvalError> {
+    binary(stack, |a, b| flag((a as u64) < (b as u64)))
+}
+
+pub fn u_gt(stack: &mut Vec<i64>) -> Result<(), EvalError> {
+    binary(stack, |a, b| flag((a as u64) > (b as u64)))
+}
+
+// ( n lo hi -- flag ) True if lo <= n < hi. The test is done on the
+// distances from lo, so it also works when the range wraps round, as in
+// Forth's standard definition: n lo - hi lo - U<
+pub fn within(stack: &mut Vec<i64>) -> Result<(), EvalError> {
+    let hi = stack.pop().ok_or(EvalError::StackUnderflow)?;
+    let lo = stack.pop().ok_or(EvalError::StackUnderflow)?;
+    let n = stack.pop().ok_or(EvalError::StackUnderflow)?;
+    stack.push(flag(
+        (n.wrapping_sub(lo) as u64) < (hi.wrapping_sub(lo) as u64),
+    ));
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -75,31 +346,34 @@ mod tests {
     #[test]
     fn test_add() {
         let mut s = vec![2, 3];
-        assert!(add(&mut s).is_ok());
+        assert!(add(&mut s, Overflow::Wrap).is_ok());
         assert_eq!(s, vec![5]);
     }
 
     #[test]
     fn test_subtract() {
         let mut s = vec![5, 2];
-        assert!(subtract(&mut s).is_ok());
+        assert!(subtract(&mut s, Overflow::Wrap).is_ok());
         assert_eq!(s, vec![3]);
     }
 
     #[test]
     fn test_multiply() {
         let mut s = vec![4, 3];
-        assert!(multiply(&mut s).is_ok());
+        assert!(multiply(&mut s, Overflow::Wrap).is_ok());
         assert_eq!(s, vec![12]);
     }
 
     #[test]
     fn test_divide() {
         let mut s = vec![10, 2];
-        assert!(divide(&mut s).is_ok());
+        assert!(divide(&mut s, Overflow::Wrap).is_ok());
         assert_eq!(s, vec![5]);
         let mut z = vec![1, 0];
-        assert_eq!(divide(&mut z), Err(EvalError::DivisionByZero));
+        assert_eq!(
+            divide(&mut z, Overflow::Wrap),
+            Err(EvalError::DivisionByZero)
+        );
     }
 
     #[test]
@@ -122,6 +396,293 @@ mod tests {
         assert_eq!(mod_op(&mut u), Err(EvalError::StackUnderflow));
     }
 
+    #[test]
+    fn test_unary_arithmetic() {
+        let mut s = vec![5];
+        assert!(one_plus(&mut s, Overflow::Wrap).is_ok());
+        assert!(one_minus(&mut s, Overflow::Wrap).is_ok());
+        assert!(one_minus(&mut s, Overflow::Wrap).is_ok());
+        assert_eq!(s, vec![4]);
+        assert!(negate(&mut s, Overflow::Wrap).is_ok());
+        assert_eq!(s, vec![-4]);
+        assert!(abs(&mut s, Overflow::Wrap).is_ok());
+        assert_eq!(s, vec![4]);
+        assert_eq!(
+            negate(&mut vec![], Overflow::Wrap),
+            Err(EvalError::StackUnderflow)
+        );
+    }
+
+    #[test]
+    fn test_min_max() {
+        let mut s = vec![3, -7];
+        assert!(min(&mut s).is_ok());
+        assert_eq!(s, vec![-7]);
+        let mut s = vec![3, -7];
+        assert!(max(&mut s).is_ok());
+        assert_eq!(s, vec![3]);
+        assert_eq!(max(&mut vec![1]), Err(EvalError::StackUnderflow));
+    }
+
+    #[test]
+    fn test_slash_mod() {
+        // Symmetric, like / and MOD: the remainder takes the dividend's sign
+        for (n, d, rem, quot) in [
+            (7, 2, 1, 3),
+            (-7, 2, -1, -3),
+            (7, -2, 1, -3),
+            (-7, -2, -1, 3),
+        ] {
+            let mut s = vec![n, d];
+            assert!(slash_mod(&mut s, Overflow::Wrap).is_ok());
+            assert_eq!(s, vec![rem, quot], "{} {} /mod", n, d);
+        }
+        assert_eq!(
+            slash_mod(&mut vec![1, 0], Overflow::Wrap),
+            Err(EvalError::DivisionByZero)
+        );
+        // The one quotient that does not fit wraps instead of panicking
+        let mut s = vec![i64::MIN, -1];
+        assert!(slash_mod(&mut s, Overflow::Wrap).is_ok());
+        assert_eq!(s, vec![0, i64::MIN]);
+    }
+
+    #[test]
+    fn test_star_slash() {
+        // The product overflows a cell, but not the intermediate result
+        let mut s = vec![i64::MAX, 4, 8];
+        assert!(star_slash(&mut s, Overflow::Wrap).is_ok());
+        assert_eq!(s, vec![i64::MAX / 2]);
+        let mut s = vec![10, 7, 3];
+        assert!(star_slash_mod(&mut s, Overflow::Wrap).is_ok());
+        assert_eq!(s, vec![1, 23]);
+        let mut s = vec![-10, 7, 3];
+        assert!(star_slash_mod(&mut s, Overflow::Wrap).is_ok());
+        assert_eq!(s, vec![-1, -23]);
+        assert_eq!(
+            star_slash(&mut vec![1, 2, 0], Overflow::Wrap),
+            Err(EvalError::DivisionByZero)
+        );
+        assert_eq!(
+            star_slash(&mut vec![1, 2], Overflow::Wrap),
+            Err(EvalError::StackUnderflow)
+        );
+    }
+
+    #[test]
+    fn test_fm_slash_mod_and_sm_slash_rem() {
+        // The examples from the Forth standard's table of division results
+        let table = [
+            (10, 7, (3, 1), (3, 1)),
+            (-10, 7, (4, -2), (-3, -1)),
+            (10, -7, (-4, -2), (3, -1)),
+            (-10, -7, (-3, 1), (-3, 1)),
+        ];
+        for (n, d, floored, symmetric) in table {
+            // Sign-extend n to a double cell
+            let hi = if n < 0 { -1 } else { 0 };
+            let mut s = vec![n, hi, d];
+            assert!(fm_slash_mod(&mut s, Overflow::Wrap).is_ok());
+            assert_eq!(s, vec![floored.0, floored.1], "{} {} fm/mod", n, d);
+            let mut s = vec![n, hi, d];
+            assert!(sm_slash_rem(&mut s, Overflow::Wrap).is_ok());
+            assert_eq!(s, vec![symmetric.0, symmetric.1], "{} {} sm/rem", n, d);
+        }
+        // A dividend that needs the high cell: 2^64 / 3
+        let mut s = vec![0, 1, 3];
+        assert!(sm_slash_rem(&mut s, Overflow::Wrap).is_ok());
+        assert_eq!(s, vec![1, 6148914691236517205]);
+        assert_eq!(
+            fm_slash_mod(&mut vec![1, 0, 0], Overflow::Wrap),
+            Err(EvalError::DivisionByZero)
+        );
+        assert_eq!(
+            sm_slash_rem(&mut vec![1, 2], Overflow::Wrap),
+            Err(EvalError::StackUnderflow)
+        );
+    }
+
+    #[test]
+    fn test_more_comparisons() {
+        type Op = fn(&mut Vec<i64>) -> Result<(), EvalError>;
+        let cases: [(Op, i64, i64, i64); 10] = [
+            (ne, 1, 2, -1),
+            (ne, 2, 2, 0),
+            (le, 2, 2, -1),
+            (le, 3, 2, 0),
+            (ge, 2, 2, -1),
+            (ge, 1, 2, 0),
+            (u_lt, 1, -1, -1),
+            (u_lt, -1, 1, 0),
+            (u_gt, -1, 1, -1),
+            (u_gt, 1, -1, 0),
+        ];
+        for (op, a, b, expected) in cases {
+            let mut s = vec![a, b];
+            assert!(op(&mut s).is_ok());
+            assert_eq!(s, vec![expected], "{} {}", a, b);
+        }
+        let mut s = vec![0];
+        assert!(zero_eq(&mut s).is_ok());
+        assert!(zero_ne(&mut s).is_ok());
+        assert!(zero_lt(&mut s).is_ok());
+        assert_eq!(s, vec![-1]);
+        assert!(zero_gt(&mut s).is_ok());
+        assert_eq!(s, vec![0]);
+        assert_eq!(zero_eq(&mut vec![]), Err(EvalError::StackUnderflow));
+    }
+
+    #[test]
+    fn test_within() {
+        for (n, lo, hi, expected) in [
+            (3, 1, 5, -1),
+            (1, 1, 5, -1),
+            (5, 1, 5, 0),
+            (0, 1, 5, 0),
+            (-2, -3, 0, -1),
+            // A range that wraps round: everything outside 5..1
+            (7, 5, 1, -1),
+            (3, 5, 1, 0),
+        ] {
+            let mut s = vec![n, lo, hi];
+            assert!(within(&mut s).is_ok());
+            assert_eq!(s, vec![expected], "{} {} {} within", n, lo, hi);
+        }
+        assert_eq!(within(&mut vec![1, 2]), Err(EvalError::StackUnderflow));
+    }
+
+    #[test]
+    fn test_overflow_wraps() {
+        let mut s = vec![i64::MAX, 1];
+        assert!(add(&mut s, Overflow::Wrap).is_ok());
+        assert_eq!(s, vec![i64::MIN]);
+        assert!(one_minus(&mut s, Overflow::Wrap).is_ok());
+        assert_eq!(s, vec![i64::MAX]);
+        let mut s = vec![i64::MIN, -1];
+        assert!(divide(&mut s, Overflow::Wrap).is_ok());
+        assert_eq!(s, vec![i64::MIN]);
+        assert!(negate(&mut s, Overflow::Wrap).is_ok());
+        assert!(abs(&mut s, Overflow::Wrap).is_ok());
+        assert_eq!(s, vec![i64::MIN]);
+        let mut s = vec![i64::MIN, -1];
+        assert!(mod_op(&mut s).is_ok());
+        assert_eq!(s, vec![0]);
+        let mut s = vec![1 << 62, 4];
+        assert!(multiply(&mut s, Overflow::Wrap).is_ok());
+        assert_eq!(s, vec![0]);
+        let mut s = vec![1 << 62];
+        assert!(two_star(&mut s, Overflow::Wrap).is_ok());
+        assert_eq!(s, vec![i64::MIN]);
+        // A quotient too big for a cell keeps its low 64 bits: 2^64 / 1
+        let mut s = vec![0, 1, 1];
+        assert!(sm_slash_rem(&mut s, Overflow::Wrap).is_ok());
+        assert_eq!(s, vec![0, 0]);
+        let mut s = vec![0, i64::MIN, -1];
+        assert!(fm_slash_mod(&mut s, Overflow::Wrap).is_ok());
+        assert_eq!(s, vec![0, 0]);
+    }
+
+    #[test]
+    fn test_overflow_checked() {
+        type Op = fn(&mut Vec<i64>, Overflow) -> Result<(), EvalError>;
+        let overflows: [(Op, Vec<i64>); 12] = [
+            (add, vec![i64::MAX, 1]),
+            (subtract, vec![i64::MIN, 1]),
+            (multiply, vec![i64::MAX, 2]),
+            (divide, vec![i64::MIN, -1]),
+            (one_plus, vec![i64::MAX]),
+            (one_minus, vec![i64::MIN]),
+            (negate, vec![i64::MIN]),
+            (abs, vec![i64::MIN]),
+            (slash_mod, vec![i64::MIN, -1]),
+            (star_slash, vec![i64::MAX, 4, 2]),
+            (sm_slash_rem, vec![0, 1, 1]),
+            (two_star, vec![1 << 62]),
+        ];
+        for (op, mut s) in overflows {
+            assert_eq!(
+                op(&mut s, Overflow::Check),
+                Err(EvalError::Overflow),
+                "{:?}",
+                s
+            );
+        }
+        assert_eq!(
+            fm_slash_mod(&mut vec![0, i64::MIN, -1], Overflow::Check),
+            Err(EvalError::Overflow)
+        );
+        // Results that fit are unaffected
+        let mut s = vec![i64::MAX - 1, 1];
+        assert!(add(&mut s, Overflow::Check).is_ok());
+        assert_eq!(s, vec![i64::MAX]);
+        let mut s = vec![i64::MAX, 4, 8];
+        assert!(star_slash(&mut s, Overflow::Check).is_ok());
+        assert_eq!(s, vec![i64::MAX / 2]);
+        let mut s = vec![-7, -1, 2];
+        assert!(fm_slash_mod(&mut s, Overflow::Check).is_ok());
+        assert_eq!(s, vec![1, -4]);
+    }
+
+    #[test]
+    fn test_bitwise() {
+        let mut s = vec![0b1100, 0b1010];
+        assert!(and(&mut s).is_ok());
+        assert_eq!(s, vec![0b1000]);
+        let mut s = vec![0b1100, 0b1010];
+        assert!(or(&mut s).is_ok());
+        assert_eq!(s, vec![0b1110]);
+        let mut s = vec![0b1100, 0b1010];
+        assert!(xor(&mut s).is_ok());
+        assert_eq!(s, vec![0b0110]);
+        let mut s = vec![0];
+        assert!(invert(&mut s).is_ok());
+        assert_eq!(s, vec![-1]);
+        assert_eq!(and(&mut vec![1]), Err(EvalError::StackUnderflow));
+        assert_eq!(invert(&mut vec![]), Err(EvalError::StackUnderflow));
+    }
+
+    #[test]
+    fn test_shifts() {
+        let mut s = vec![1, 4];
+        assert!(lshift(&mut s).is_ok());
+        assert_eq!(s, vec![16]);
+        let mut s = vec![-16, 2];
+        assert!(rshift(&mut s).is_ok());
+        assert_eq!(s, vec![(-16i64 as u64 >> 2) as i64]);
+        let mut s = vec![-16, 2];
+        assert!(arshift(&mut s).is_ok());
+        assert_eq!(s, vec![-4]);
+        let mut s = vec![-3];
+        assert!(two_star(&mut s, Overflow::Wrap).is_ok());
+        assert_eq!(s, vec![-6]);
+        let mut s = vec![5];
+        assert!(two_slash(&mut s).is_ok());
+        assert_eq!(s, vec![2]);
+        let mut s = vec![-1];
+        assert!(two_slash(&mut s).is_ok());
+        assert_eq!(s, vec![-1]);
+        assert_eq!(lshift(&mut vec![1]), Err(EvalError::StackUnderflow));
+    }
+
+    #[test]
+    fn test_shifts_out_of_range() {
+        // Counts of 64 or more, or negative ones, shift every bit out
+        for count in [64, 65, 1000, -1, i64::MIN] {
+            let mut s = vec![-1, count, -1, count, -1, count, 1, count];
+            assert!(arshift(&mut s).is_ok());
+            assert_eq!(s.pop(), Some(0));
+            assert!(arshift(&mut s).is_ok());
+            assert_eq!(s.pop(), Some(-1));
+            assert!(rshift(&mut s).is_ok());
+            assert_eq!(s.pop(), Some(0));
+            assert!(lshift(&mut s).is_ok());
+            assert_eq!(s.pop(), Some(0));
+        }
+        let mut s = vec![1, 63];
+        assert!(lshift(&mut s).is_ok());
+        assert_eq!(s, vec![i64::MIN]);
+    }
+
     #[test]
     fn test_eq() {
         let mut s = vec![2, 2];
@@ -1,7 +1,11 @@
-use crate::eval::{DictEntry, eval}; // Removed EvalError import
-use crate::token::Token;
-use std::collections::HashMap;
+use crate::eval::{NativeWord, eval, execute}; // Removed EvalError import
+use crate::forth::{Forth, STRING_ADDR, STRING_SIZE};
+use crate::io_ops;
+use crate::memory_ops;
+use crate::token::{Location, Number, Token, parse_number, read_number};
 use std::fmt;
+use std::io;
+use std::vec;
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum ForthOp {
@@ -11,7 +15,28 @@ pub enum ForthOp {
     Subtract,
     Multiply,
     Divide,
-    Mod, // Added Mod
+    Mod,          // Added Mod
+    OnePlus,      // 1+
+    OneMinus,     // 1-
+    Negate,       // negate
+    Abs,          // abs
+    Min,          // min
+    Max,          // max
+    SlashMod,     // /mod
+    StarSlash,    // */
+    StarSlashMod, // */mod
+    FmSlashMod,   // fm/mod
+    SmSlashRem,   // sm/rem
+    // Bitwise
+    And,      // and
+    Or,       // or
+    Xor,      // xor
+    Invert,   // invert
+    LShift,   // lshift
+    RShift,   // rshift
+    ARShift,  // arshift
+    TwoStar,  // 2*
+    TwoSlash, // 2/
     // Stack
     Dup,      // dup
     Drop,     // drop
@@ -24,28 +49,147 @@ pub enum ForthOp {
     TwoSwap,  // 2swap
     TwoOver,  // 2over
     MinusRot, // -rot
+    // Memory
+    Here,      // here
+    Allot,     // allot
+    Comma,     // ,
+    CComma,    // c,
+    Fetch,     // @
+    Store,     // !
+    TwoFetch,  // 2@
+    TwoStore,  // 2!
+    CFetch,    // c@
+    CStore,    // c!
+    PlusStore, // +!
+    Cells,     // cells
+    CellPlus,  // cell+
+    Chars,     // chars
+    Align,     // align
+    Aligned,   // aligned
     // Output
-    Print,      // .
-    PrintStack, // .s
+    Print,               // .
+    PrintStack,          // .s
+    PrintString(String), // ." text" and .( text)
+    Type,                // type
+    Emit,                // emit
+    Cr,                  // cr
+    Space,               // space
+    Spaces,              // spaces
+    Bl,                  // bl
+    // Number formatting
+    Hex,               // hex
+    Decimal,           // decimal
+    Binary,            // binary
+    Base,              // base
+    UDot,              // u.
+    DotR,              // .r
+    UDotR,             // u.r
+    LessNumberSign,    // <#
+    NumberSign,        // #
+    NumberSignS,       // #s
+    Hold,              // hold
+    Sign,              // sign
+    NumberSignGreater, // #>
+    // Input
+    Key,         // key
+    KeyQuestion, // key?
+    Accept,      // accept
+    // Strings
+    SLiteral(i64, i64), // S" text": pushes the address and length of text stored in data space
+    Count,              // count
     // Other
-    Word(String),                       // For words not yet defined or handled
+    // Calls, along with where they were written when that is known. A word not
+    // defined yet is looked up by name when it runs; a dictionary word is
+    // called by the execution token it had when compiled.
+    Word(String, Option<Location>),
+    Call(usize, Option<Location>),
+    Native(NativeWord),                 // Host function registered from Rust
     Define(String, Vec<ForthOp>, bool), // Name, body, and immediate flag
+    // Defining words. The name is parsed from the following token in interpret
+    // mode; inside a definition it is None and read from the input at runtime.
+    Variable(Option<String>),    // VARIABLE name
+    TwoVariable(Option<String>), // 2VARIABLE name
+    Constant(Option<String>),    // x CONSTANT name
+    TwoConstant(Option<String>), // x1 x2 2CONSTANT name
+    Value(Option<String>),       // x VALUE name
+    Create(Option<String>),      // CREATE name
+    Does(Vec<ForthOp>),          // DOES> code: sets the latest CREATEd word's behaviour
+    Defer(Option<String>),       // DEFER name
+    To(String),                  // x TO name
+    // Execution tokens
+    Tick(Option<String>), // ' name: parsed like a defining word's name
+    Execute,              // execute
+    Is(String),           // xt IS name
+    ActionOf(String),     // ACTION-OF name
+    // Exceptions
+    Catch,              // catch
+    Throw,              // throw
+    Abort,              // abort
+    AbortQuote(String), // ABORT" text"
+    // Compiler access
+    State,            // state: address of the flag that is true while compiling
+    CompileComma,     // compile,: appends a call to an xt to the current definition
+    Postpone(String), // A POSTPONEd built-in: compiles that word when run
     // Conditional: IF-ELSE-THEN branches
     IfElse(Vec<ForthOp>, Vec<ForthOp>),
+    // Indefinite loops
+    BeginUntil(Vec<ForthOp>),                     // BEGIN body UNTIL
+    BeginWhileRepeat(Vec<ForthOp>, Vec<ForthOp>), // BEGIN condition WHILE body REPEAT
+    BeginAgain(Vec<ForthOp>),                     // BEGIN body AGAIN
+    // CASE clauses and default ops; the selector is dropped at ENDCASE
+    Case(Vec<CaseClause>, Vec<ForthOp>),
+    // Flat control flow, which the structured ops above become when a word is
+    // defined. Offsets count from the jumping op.
+    Branch(isize),         // Jump by offset
+    ZeroBranch(isize),     // Pop a flag and jump by offset if it is zero
+    DoBranch(isize),       // DO: start a loop, or jump past its end if it runs no times
+    QDoBranch(isize),      // ?DO
+    LoopBranch(isize),     // LOOP: step the index and jump back unless the loop is done
+    PlusLoopBranch(isize), // +LOOP
+    LeaveBranch(isize),    // LEAVE: drop the loop and jump past its end
     // Comparisons
     Eq,
     Lt,
     Gt,
+    Ne,     // <>
+    Le,     // <=
+    Ge,     // >=
+    ZeroEq, // 0=
+    ZeroNe, // 0<>
+    ZeroLt, // 0<
+    ZeroGt, // 0>
+    ULt,    // u<
+    UGt,    // u>
+    Within, // within
     // Loop constructs (compile-time only)
     Do,
+    QDo, // ?do: like DO, but skips the loop when start = limit
     Loop,
-    I, // Pushes current loop index
-    // J, // Pushes outer loop index (for nested loops - maybe later)
-    // Leave, // Exits innermost loop immediately (maybe later)
+    PlusLoop, // +loop: adds the top of stack to the index
+    I,        // Pushes current loop index
+    J,        // Pushes the index of the next outer loop
+    K,        // Pushes the index of the second outer loop
+    Leave,    // Exits innermost loop immediately
+    Unloop,   // Discards the innermost loop's control parameters
+    Exit,     // Returns from the current definition
+    // Calls the definition being compiled, with where RECURSE was read
+    Recurse(Option<Location>),
+    // Return stack
+    ToR,       // >r
+    RFrom,     // r>
+    RFetch,    // r@
+    TwoToR,    // 2>r
+    TwoRFrom,  // 2r>
+    TwoRFetch, // 2r@
+    RDrop,     // rdrop
     // New operation for immediate mode
     Immediate, // Sets the most recently defined word to immediate
 }
 
+// One `value OF body ENDOF` clause of a CASE: the ops that push the value to
+// compare against, and the ops to run when it matches
+pub type CaseClause = (Vec<ForthOp>, Vec<ForthOp>);
+
 impl fmt::Display for ForthOp {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -53,6 +197,36 @@ impl fmt::Display for ForthOp {
             ForthOp::Add => write!(f, "Add"),
             ForthOp::Subtract => write!(f, "Subtract"),
             ForthOp::Multiply => write!(f, "Multiply"),
+            ForthOp::OnePlus => write!(f, "OnePlus"),
+            ForthOp::OneMinus => write!(f, "OneMinus"),
+            ForthOp::Negate => write!(f, "Negate"),
+            ForthOp::Abs => write!(f, "Abs"),
+            ForthOp::Min => write!(f, "Min"),
+            ForthOp::Max => write!(f, "Max"),
+            ForthOp::SlashMod => write!(f, "SlashMod"),
+            ForthOp::StarSlash => write!(f, "StarSlash"),
+            ForthOp::StarSlashMod => write!(f, "StarSlashMod"),
+            ForthOp::FmSlashMod => write!(f, "FmSlashMod"),
+            ForthOp::SmSlashRem => write!(f, "SmSlashRem"),
+            ForthOp::Ne => write!(f, "Ne"),
+            ForthOp::Le => write!(f, "Le"),
+            ForthOp::Ge => write!(f, "Ge"),
+            ForthOp::ZeroEq => write!(f, "ZeroEq"),
+            ForthOp::ZeroNe => write!(f, "ZeroNe"),
+            ForthOp::ZeroLt => write!(f, "ZeroLt"),
+            ForthOp::ZeroGt => write!(f, "ZeroGt"),
+            ForthOp::ULt => write!(f, "ULt"),
+            ForthOp::UGt => write!(f, "UGt"),
+            ForthOp::Within => write!(f, "Within"),
+            ForthOp::And => write!(f, "And"),
+            ForthOp::Or => write!(f, "Or"),
+            ForthOp::Xor => write!(f, "Xor"),
+            ForthOp::Invert => write!(f, "Invert"),
+            ForthOp::LShift => write!(f, "LShift"),
+            ForthOp::RShift => write!(f, "RShift"),
+            ForthOp::ARShift => write!(f, "ARShift"),
+            ForthOp::TwoStar => write!(f, "TwoStar"),
+            ForthOp::TwoSlash => write!(f, "TwoSlash"),
             ForthOp::Divide => write!(f, "Divide"),
             ForthOp::Mod => write!(f, "Mod"), // Added Mod
             ForthOp::Dup => write!(f, "Dup"),
@@ -66,21 +240,112 @@ impl fmt::Display for ForthOp {
             ForthOp::TwoSwap => write!(f, "TwoSwap"),
             ForthOp::TwoOver => write!(f, "TwoOver"),
             ForthOp::MinusRot => write!(f, "MinusRot"),
+            ForthOp::Here => write!(f, "Here"),
+            ForthOp::Allot => write!(f, "Allot"),
+            ForthOp::Comma => write!(f, "Comma"),
+            ForthOp::CComma => write!(f, "CComma"),
+            ForthOp::Fetch => write!(f, "Fetch"),
+            ForthOp::Store => write!(f, "Store"),
+            ForthOp::TwoFetch => write!(f, "TwoFetch"),
+            ForthOp::TwoStore => write!(f, "TwoStore"),
+            ForthOp::CFetch => write!(f, "CFetch"),
+            ForthOp::CStore => write!(f, "CStore"),
+            ForthOp::PlusStore => write!(f, "PlusStore"),
+            ForthOp::Cells => write!(f, "Cells"),
+            ForthOp::CellPlus => write!(f, "CellPlus"),
+            ForthOp::Chars => write!(f, "Chars"),
+            ForthOp::Align => write!(f, "Align"),
+            ForthOp::Aligned => write!(f, "Aligned"),
             ForthOp::Print => write!(f, "Print"),
             ForthOp::PrintStack => write!(f, "PrintStack"),
-            ForthOp::Word(s) => write!(f, "Word({})", s),
+            ForthOp::PrintString(text) => write!(f, "PrintString({:?})", text),
+            ForthOp::Type => write!(f, "Type"),
+            ForthOp::Emit => write!(f, "Emit"),
+            ForthOp::Cr => write!(f, "Cr"),
+            ForthOp::Space => write!(f, "Space"),
+            ForthOp::Spaces => write!(f, "Spaces"),
+            ForthOp::Bl => write!(f, "Bl"),
+            ForthOp::Hex => write!(f, "Hex"),
+            ForthOp::Decimal => write!(f, "Decimal"),
+            ForthOp::Binary => write!(f, "Binary"),
+            ForthOp::Base => write!(f, "Base"),
+            ForthOp::UDot => write!(f, "UDot"),
+            ForthOp::DotR => write!(f, "DotR"),
+            ForthOp::UDotR => write!(f, "UDotR"),
+            ForthOp::LessNumberSign => write!(f, "LessNumberSign"),
+            ForthOp::NumberSign => write!(f, "NumberSign"),
+            ForthOp::NumberSignS => write!(f, "NumberSignS"),
+            ForthOp::Hold => write!(f, "Hold"),
+            ForthOp::Sign => write!(f, "Sign"),
+            ForthOp::NumberSignGreater => write!(f, "NumberSignGreater"),
+            ForthOp::Key => write!(f, "Key"),
+            ForthOp::KeyQuestion => write!(f, "KeyQuestion"),
+            ForthOp::Accept => write!(f, "Accept"),
+            ForthOp::SLiteral(addr, len) => write!(f, "SLiteral({}, {})", addr, len),
+            ForthOp::Count => write!(f, "Count"),
+            ForthOp::Word(s, _) => write!(f, "Word({})", s),
+            ForthOp::Call(xt, _) => write!(f, "Call({})", xt),
+            ForthOp::Native(native) => write!(f, "Native({})", native.name),
             ForthOp::Define(name, ops, immediate) => {
                 write!(f, "Define({}, {:?}, {})", name, ops, immediate)
             } // Added
+            ForthOp::Variable(name) => write!(f, "Variable({:?})", name),
+            ForthOp::TwoVariable(name) => write!(f, "TwoVariable({:?})", name),
+            ForthOp::Constant(name) => write!(f, "Constant({:?})", name),
+            ForthOp::TwoConstant(name) => write!(f, "TwoConstant({:?})", name),
+            ForthOp::Value(name) => write!(f, "Value({:?})", name),
+            ForthOp::Create(name) => write!(f, "Create({:?})", name),
+            ForthOp::Does(code) => write!(f, "Does({:?})", code),
+            ForthOp::Defer(name) => write!(f, "Defer({:?})", name),
+            ForthOp::Tick(name) => write!(f, "Tick({:?})", name),
+            ForthOp::Execute => write!(f, "Execute"),
+            ForthOp::Is(name) => write!(f, "Is({})", name),
+            ForthOp::ActionOf(name) => write!(f, "ActionOf({})", name),
+            ForthOp::To(name) => write!(f, "To({})", name),
+            ForthOp::State => write!(f, "State"),
+            ForthOp::CompileComma => write!(f, "CompileComma"),
+            ForthOp::Postpone(name) => write!(f, "Postpone({})", name),
             ForthOp::IfElse(then_ops, else_ops) => {
                 write!(f, "IfElse({:?}, {:?})", then_ops, else_ops)
             }
+            ForthOp::BeginUntil(body) => write!(f, "BeginUntil({:?})", body),
+            ForthOp::BeginWhileRepeat(cond, body) => {
+                write!(f, "BeginWhileRepeat({:?}, {:?})", cond, body)
+            }
+            ForthOp::BeginAgain(body) => write!(f, "BeginAgain({:?})", body),
+            ForthOp::Case(clauses, default) => write!(f, "Case({:?}, {:?})", clauses, default),
+            ForthOp::Catch => write!(f, "Catch"),
+            ForthOp::Throw => write!(f, "Throw"),
+            ForthOp::Abort => write!(f, "Abort"),
+            ForthOp::AbortQuote(text) => write!(f, "AbortQuote({:?})", text),
+            ForthOp::Branch(offset) => write!(f, "Branch({})", offset),
+            ForthOp::ZeroBranch(offset) => write!(f, "ZeroBranch({})", offset),
+            ForthOp::DoBranch(offset) => write!(f, "DoBranch({})", offset),
+            ForthOp::QDoBranch(offset) => write!(f, "QDoBranch({})", offset),
+            ForthOp::LoopBranch(offset) => write!(f, "LoopBranch({})", offset),
+            ForthOp::PlusLoopBranch(offset) => write!(f, "PlusLoopBranch({})", offset),
+            ForthOp::LeaveBranch(offset) => write!(f, "LeaveBranch({})", offset),
             ForthOp::Eq => write!(f, "Eq"),
             ForthOp::Lt => write!(f, "Lt"),
             ForthOp::Gt => write!(f, "Gt"),
             ForthOp::Do => write!(f, "Do"),
+            ForthOp::QDo => write!(f, "QDo"),
             ForthOp::Loop => write!(f, "Loop"),
+            ForthOp::PlusLoop => write!(f, "PlusLoop"),
             ForthOp::I => write!(f, "I"),
+            ForthOp::J => write!(f, "J"),
+            ForthOp::K => write!(f, "K"),
+            ForthOp::Leave => write!(f, "Leave"),
+            ForthOp::Unloop => write!(f, "Unloop"),
+            ForthOp::Exit => write!(f, "Exit"),
+            ForthOp::Recurse(_) => write!(f, "Recurse"),
+            ForthOp::ToR => write!(f, "ToR"),
+            ForthOp::RFrom => write!(f, "RFrom"),
+            ForthOp::RFetch => write!(f, "RFetch"),
+            ForthOp::TwoToR => write!(f, "TwoToR"),
+            ForthOp::TwoRFrom => write!(f, "TwoRFrom"),
+            ForthOp::TwoRFetch => write!(f, "TwoRFetch"),
+            ForthOp::RDrop => write!(f, "RDrop"),
             ForthOp::Immediate => write!(f, "Immediate"),
         }
     }
@@ -94,9 +359,47 @@ pub enum ParseError {
     UnterminatedDefinition,       // E.g., Reached end of input inside definition
     NestedDefinitionNotSupported, // E.g., Colon inside a definition
     UnterminatedConditional,
-    MismatchedDoLoop,                     // Added
+    MismatchedConditional,                // ELSE or THEN without a matching IF
+    MismatchedDoLoop, // Also LEAVE, UNLOOP, J or K without enough enclosing DO loops
+    MismatchedBeginLoop, // UNTIL, WHILE, REPEAT or AGAIN without a matching BEGIN
+    MismatchedCase,   // OF, ENDOF or ENDCASE without a matching CASE (or OF)
     ControlWordOutsideDefinition(String), // Added: e.g., DO outside : ... ;
-    ImmediateWordError(String),           // Added: Error during immediate word execution
+    UnknownWord(String), // ['] of a word that is not defined (yet)
+    ImmediateWordError(String), // Added: Error during immediate word execution
+    StringTooLong, // C" text longer than a counted string can hold, or S" than a transient buffer
+    MalformedNumber(String), // A literal like $FG or 'AB', or digits too big for a cell
+}
+
+// Words that only make sense inside a definition
+pub(crate) const CONTROL_WORDS: &[&str] = &[
+    "if", "else", "then", "do", "?do", "loop", "+loop", "i", "j", "k", "leave", "unloop", "begin",
+    "until", "while", "repeat", "again", "exit", "recurse", "case", "of", "endof", "endcase",
+    "does>", "[']", ">r", "r>", "r@", "2>r", "2r>", "2r@", "rdrop", "[", "]", "literal",
+    "postpone",
+];
+
+// Control structures left open while compiling a definition. Each one owns
+// the ops compiled since it was opened.
+#[derive(Debug)]
+enum ControlFrame {
+    If(Vec<ForthOp>),                                // IF branch
+    Else(Vec<ForthOp>, Vec<ForthOp>),                // finished IF branch, ELSE branch
+    Do(ForthOp, Vec<ForthOp>),                       // DO or ?DO, and the loop body
+    Begin(Vec<ForthOp>),                             // BEGIN body (or condition, if WHILE follows)
+    While(Vec<ForthOp>, Vec<ForthOp>),               // finished condition, WHILE body
+    Case(Vec<CaseClause>, Vec<ForthOp>),             // finished clauses, OF value or default
+    Of(Vec<CaseClause>, Vec<ForthOp>, Vec<ForthOp>), // finished clauses, OF value, OF body
+}
+
+// A definition being compiled. It lives in the VM while the definition is
+// open, so immediate words can add to it with LITERAL, COMPILE, and POSTPONE.
+#[derive(Debug, Default)]
+pub(crate) struct Compiler {
+    name: String,
+    body: Vec<ForthOp>,
+    control_stack: Vec<ControlFrame>, // Open IF/DO/BEGIN structures
+    does_parts: Vec<Vec<ForthOp>>,    // Code before each DOES>
+    interpreting: bool,               // Between [ and ]
 }
 
 // Helper function to parse a single token into a ForthOp (used in interpret and compile modes)
@@ -114,8 +417,64 @@ fn parse_token_to_op(token: Token) -> Option<ForthOp> {
                 "*" => Some(ForthOp::Multiply),
                 "/" => Some(ForthOp::Divide),
                 "mod" => Some(ForthOp::Mod), // Added mod
+                "1+" => Some(ForthOp::OnePlus),
+                "1-" => Some(ForthOp::OneMinus),
+                "negate" => Some(ForthOp::Negate),
+                "abs" => Some(ForthOp::Abs),
+                "min" => Some(ForthOp::Min),
+                "max" => Some(ForthOp::Max),
+                "/mod" => Some(ForthOp::SlashMod),
+                "*/" => Some(ForthOp::StarSlash),
+                "*/mod" => Some(ForthOp::StarSlashMod),
+                "fm/mod" => Some(ForthOp::FmSlashMod),
+                "sm/rem" => Some(ForthOp::SmSlashRem),
+                "<>" => Some(ForthOp::Ne),
+                "<=" => Some(ForthOp::Le),
+                ">=" => Some(ForthOp::Ge),
+                "0=" => Some(ForthOp::ZeroEq),
+                "0<>" => Some(ForthOp::ZeroNe),
+                "0<" => Some(ForthOp::ZeroLt),
+                "0>" => Some(ForthOp::ZeroGt),
+                "u<" => Some(ForthOp::ULt),
+                "u>" => Some(ForthOp::UGt),
+                "within" => Some(ForthOp::Within),
+                "and" => Some(ForthOp::And),
+                "or" => Some(ForthOp::Or),
+                "xor" => Some(ForthOp::Xor),
+                "invert" => Some(ForthOp::Invert),
+                "lshift" => Some(ForthOp::LShift),
+                "rshift" => Some(ForthOp::RShift),
+                "arshift" => Some(ForthOp::ARShift),
+                "2*" => Some(ForthOp::TwoStar),
+                "2/" => Some(ForthOp::TwoSlash),
+                // Flags are all bits set or all clear
+                "true" => Some(ForthOp::Push(-1)),
+                "false" => Some(ForthOp::Push(0)),
                 "." => Some(ForthOp::Print),
                 ".s" => Some(ForthOp::PrintStack),
+                "type" => Some(ForthOp::Type),
+                "emit" => Some(ForthOp::Emit),
+                "cr" => Some(ForthOp::Cr),
+                "space" => Some(ForthOp::Space),
+                "spaces" => Some(ForthOp::Spaces),
+                "bl" => Some(ForthOp::Bl),
+                "hex" => Some(ForthOp::Hex),
+                "decimal" => Some(ForthOp::Decimal),
+                "binary" => Some(ForthOp::Binary),
+                "base" => Some(ForthOp::Base),
+                "u." => Some(ForthOp::UDot),
+                ".r" => Some(ForthOp::DotR),
+                "u.r" => Some(ForthOp::UDotR),
+                "<#" => Some(ForthOp::LessNumberSign),
+                "#" => Some(ForthOp::NumberSign),
+                "#s" => Some(ForthOp::NumberSignS),
+                "hold" => Some(ForthOp::Hold),
+                "sign" => Some(ForthOp::Sign),
+                "#>" => Some(ForthOp::NumberSignGreater),
+                "key" => Some(ForthOp::Key),
+                "key?" => Some(ForthOp::KeyQuestion),
+                "accept" => Some(ForthOp::Accept),
+                "count" => Some(ForthOp::Count),
                 "dup" => Some(ForthOp::Dup),
                 "drop" => Some(ForthOp::Drop),
                 "swap" => Some(ForthOp::Swap),
@@ -127,8 +486,37 @@ fn parse_token_to_op(token: Token) -> Option<ForthOp> {
                 "2swap" => Some(ForthOp::TwoSwap),
                 "2over" => Some(ForthOp::TwoOver),
                 "-rot" => Some(ForthOp::MinusRot),
+                "here" => Some(ForthOp::Here),
+                "allot" => Some(ForthOp::Allot),
+                "," => Some(ForthOp::Comma),
+                "c," => Some(ForthOp::CComma),
+                "@" => Some(ForthOp::Fetch),
+                "!" => Some(ForthOp::Store),
+                "2@" => Some(ForthOp::TwoFetch),
+                "2!" => Some(ForthOp::TwoStore),
+                "c@" => Some(ForthOp::CFetch),
+                "c!" => Some(ForthOp::CStore),
+                "+!" => Some(ForthOp::PlusStore),
+                "cells" => Some(ForthOp::Cells),
+                "cell+" => Some(ForthOp::CellPlus),
+                "chars" => Some(ForthOp::Chars),
+                "align" => Some(ForthOp::Align),
+                "aligned" => Some(ForthOp::Aligned),
+                ">r" => Some(ForthOp::ToR),
+                "r>" => Some(ForthOp::RFrom),
+                "r@" => Some(ForthOp::RFetch),
+                "2>r" => Some(ForthOp::TwoToR),
+                "2r>" => Some(ForthOp::TwoRFrom),
+                "2r@" => Some(ForthOp::TwoRFetch),
+                "rdrop" => Some(ForthOp::RDrop),
+                "execute" => Some(ForthOp::Execute),
+                "catch" => Some(ForthOp::Catch),
+                "throw" => Some(ForthOp::Throw),
+                "abort" => Some(ForthOp::Abort),
+                "state" => Some(ForthOp::State),
+                "compile," => Some(ForthOp::CompileComma),
                 "immediate" => Some(ForthOp::Immediate),
-                _ => Some(ForthOp::Word(s)),
+                _ => Some(ForthOp::Word(s, None)),
             }
         }
         // Colon and Semicolon handled in parse(), skip other tokens
@@ -136,264 +524,561 @@ fn parse_token_to_op(token: Token) -> Option<ForthOp> {
     }
 }
 
-pub fn parse(tokens: Vec<Token>) -> Result<Vec<ForthOp>, ParseError> {
-    let mut ops = Vec::new();
-    let mut token_iter = tokens.into_iter().peekable();
-    let mut compiling = false; // Are we inside a : ... ; definition?
-    let mut current_def_name: Option<String> = None;
-    let mut current_def_body: Vec<ForthOp> = Vec::new();
-    let mut loop_depth = 0; // Track DO...LOOP balance within definition
-    let mut dictionary: HashMap<String, DictEntry> = HashMap::new();
-    let mut latest_word: Option<String> = None;
-    let mut stack: Vec<i64> = Vec::new();
-    let mut loop_control_stack: Vec<(usize, i64, i64)> = Vec::new();
+// The op for a word that is not a control or parsing word: a built-in, a
+// call to a dictionary word, a number in the current BASE, or a call by name
+// to a word defined later. Dictionary words win over numbers, so a word named
+// ADD still works in HEX. Calls remember where the word was read, `at`.
+fn word_op(word: &str, at: Option<Location>, vm: &Forth) -> Result<ForthOp, ParseError> {
+    Ok(match parse_token_to_op(Token::Word(word.to_string())) {
+        Some(ForthOp::Word(name, _)) => match vm.lookup_xt(&name.to_uppercase()) {
+            Some(xt) => ForthOp::Call(xt, at),
+            None => match read_number(&name, vm.base()) {
+                Number::Cell(n) => ForthOp::Push(n),
+                Number::TooBig => return Err(ParseError::MalformedNumber(name)),
+                Number::NotANumber => ForthOp::Word(name, at),
+            },
+        },
+        Some(op) => op,
+        None => ForthOp::Word(word.to_string(), at),
+    })
+}
 
-    while let Some(token) = token_iter.next() {
-        // Skip whitespace and comments
-        if matches!(
-            token,
-            Token::Whitespace | Token::Comment | Token::LineComment
-        ) {
-            continue;
+// The name read by : and the defining words. Anything that looks like a
+// decimal number is not a name.
+pub(crate) fn word_name(token: Option<Token>) -> Option<String> {
+    match token {
+        Some(Token::Word(name)) if parse_number(&name, 10).is_none() => Some(name),
+        _ => None,
+    }
+}
+
+// The op for a built-in word like DUP or +, which has no dictionary entry
+pub(crate) fn primitive_op(name: &str) -> Option<ForthOp> {
+    match parse_token_to_op(Token::Word(name.to_string())) {
+        Some(ForthOp::Word(..)) | None => None,
+        op => op,
+    }
+}
+
+// The op for a string token, or None for any other token. When compiling,
+// S" and C" store their text in the data space as they are read, so a
+// definition that uses them only holds its address. When interpreting, the
+// text goes to the next transient buffer instead and HERE stays put.
+fn string_op(
+    token: &Token,
+    compiling: bool,
+    vm: &mut Forth,
+) -> Result<Option<ForthOp>, ParseError> {
+    let store = |vm: &mut Forth, bytes: &[u8], word: &str| {
+        let stored = if compiling {
+            memory_ops::store_bytes(&mut vm.memory, bytes)
+        } else if bytes.len() > STRING_SIZE {
+            return Err(ParseError::StringTooLong);
+        } else {
+            let addr = STRING_ADDR + (vm.string_buffer * STRING_SIZE) as i64;
+            vm.string_buffer = (vm.string_buffer + 1) % 2;
+            memory_ops::bytes_mut(&mut vm.memory, addr, bytes.len() as i64)
+                .map(|buffer| buffer.copy_from_slice(bytes))
+                .map(|_| addr)
+        };
+        stored.map_err(|e| ParseError::ImmediateWordError(format!("{}: {}", word, e)))
+    };
+    let op = match token {
+        Token::DotQuote(text) | Token::DotParen(text) => ForthOp::PrintString(text.clone()),
+        Token::AbortQuote(text) => ForthOp::AbortQuote(text.clone()),
+        Token::SQuote(bytes) => {
+            let addr = store(vm, bytes, "S\"")?;
+            ForthOp::SLiteral(addr, bytes.len() as i64)
+        }
+        Token::CQuote(bytes) => {
+            // A counted string starts with its length in one byte
+            let Ok(len) = u8::try_from(bytes.len()) else {
+                return Err(ParseError::StringTooLong);
+            };
+            let mut counted = vec![len];
+            counted.extend(bytes);
+            ForthOp::Push(store(vm, &counted, "C\"")?)
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some(op))
+}
+
+// Words that parse the name following them, like VARIABLE X or TO X.
+// Returns None if `word` is not one of them. When compiling, defining words
+// leave the name to be read from the input each time the definition runs.
+fn parse_named_word(
+    word: &str,
+    token_iter: &mut vec::IntoIter<Token>,
+    compiling: bool,
+) -> Result<Option<ForthOp>, ParseError> {
+    let make_op: fn(Option<String>) -> ForthOp = match word.to_lowercase().as_str() {
+        "variable" => ForthOp::Variable,
+        "2variable" => ForthOp::TwoVariable,
+        "constant" => ForthOp::Constant,
+        "2constant" => ForthOp::TwoConstant,
+        "value" => ForthOp::Value,
+        "create" => ForthOp::Create,
+        "defer" => ForthOp::Defer,
+        "'" => ForthOp::Tick,
+        // These always take their name when compiled
+        "to" | "is" | "action-of" => {
+            let Some(name) = word_name(token_iter.next()) else {
+                return Err(ParseError::ExpectedWordName);
+            };
+            let name = name.to_uppercase();
+            return Ok(Some(match word.to_lowercase().as_str() {
+                "to" => ForthOp::To(name),
+                "is" => ForthOp::Is(name),
+                _ => ForthOp::ActionOf(name),
+            }));
         }
+        _ => return Ok(None),
+    };
+    if compiling {
+        return Ok(Some(make_op(None)));
+    }
+    match word_name(token_iter.next()) {
+        Some(name) => Ok(Some(make_op(Some(name.to_uppercase())))),
+        None => Err(ParseError::ExpectedWordName),
+    }
+}
 
-        if compiling {
-            // Compile-only IF ... ELSE ... THEN
-            if let Token::Word(s) = &token {
-                // Handle immediate words during compilation
-                let upper_s = s.to_uppercase();
-                if let Some(entry) = dictionary.get(&upper_s) {
-                    if entry.immediate {
-                        // Clone the body to avoid borrow issues
-                        let immediate_body = entry.body.clone();
-                        // Execute immediate word directly during compilation
-                        if let Err(e) = eval(
-                            &immediate_body,
-                            &mut stack,
-                            &mut dictionary,
-                            &mut loop_control_stack,
-                            &mut latest_word,
-                        ) {
-                            // Convert EvalError to ParseError
-                            return Err(ParseError::ImmediateWordError(format!("{}: {}", s, e)));
-                        }
-                        continue; // Skip adding to definition
-                    }
+// Parse a whole token sequence into interpret-mode ops, compiling definitions
+// against the VM's dictionary
+pub fn parse(tokens: Vec<Token>, vm: &mut Forth) -> Result<Vec<ForthOp>, ParseError> {
+    let mut ops = Vec::new();
+    let mut token_iter = tokens.into_iter();
+    loop {
+        match parse_next(&mut token_iter, vm) {
+            Ok(Some(op)) => ops.push(op),
+            Ok(None) => return Ok(ops),
+            Err(e) => {
+                if e == ParseError::UnterminatedDefinition {
+                    // No more lines will come to finish it
+                    close_definition(vm);
                 }
+                return Err(e);
+            }
+        }
+    }
+}
 
-                if s.to_lowercase() == "if" {
-                    // Collect then- and else- tokens
-                    let mut then_toks = Vec::new();
-                    let mut else_toks = Vec::new();
-                    let mut depth = 1;
-                    let mut in_else = false;
-                    while let Some(next_tok) = token_iter.next() {
-                        if let Token::Word(w) = &next_tok {
-                            let wl = w.to_lowercase();
-                            if wl == "if" {
-                                depth += 1;
-                            } else if wl == "else" && depth == 1 {
-                                in_else = true;
-                                continue;
-                            } else if wl == "then" {
-                                depth -= 1;
-                                if depth == 0 {
-                                    break;
-                                }
-                            }
-                        }
-                        if in_else {
-                            else_toks.push(next_tok.clone());
-                        } else {
-                            then_toks.push(next_tok.clone());
-                        }
-                    }
-                    if depth != 0 {
-                        return Err(ParseError::UnterminatedConditional);
-                    }
-                    // Parse branches and append to definition body
-                    let then_ops = parse(then_toks)?;
-                    let else_ops = if in_else {
-                        parse(else_toks)?
-                    } else {
-                        Vec::new()
-                    };
-                    current_def_body.push(ForthOp::IfElse(then_ops, else_ops));
-                    continue;
+// Parse the next interpret-mode op: a single word or number, or a whole
+// `: ... ;` definition as a Define op. Returns None at the end of the input.
+// A definition still open there stays in vm.compiler, and the error is
+// UnterminatedDefinition; continue_definition goes on with the next line.
+pub fn parse_next(
+    token_iter: &mut vec::IntoIter<Token>,
+    vm: &mut Forth,
+) -> Result<Option<ForthOp>, ParseError> {
+    while let Some(token) = token_iter.next() {
+        // Interpret mode: compile-only words treated as data
+        match token {
+            // Skip whitespace and comments
+            Token::Whitespace | Token::Comment | Token::LineComment => continue,
+            Token::Colon => {
+                // Start new definition
+                let name = match word_name(token_iter.next()) {
+                    Some(name) => name.to_uppercase(),
+                    None => return Err(ParseError::ExpectedWordName),
+                };
+                if vm.compiler.is_some() {
+                    return Err(ParseError::NestedDefinitionNotSupported);
                 }
+                vm.compiler = Some(Compiler {
+                    name,
+                    ..Compiler::default()
+                });
+                vm.set_state(true);
+                return continue_definition(token_iter, vm).map(Some);
             }
-            match token {
-                Token::Semicolon => {
-                    if loop_depth != 0 {
-                        return Err(ParseError::MismatchedDoLoop);
-                    }
-                    // End definition
-                    let name = current_def_name.take().unwrap();
-                    ops.push(ForthOp::Define(
-                        name.clone(),
-                        current_def_body.clone(),
-                        false,
-                    ));
-
-                    // Update dictionary and latest_word for subsequent IMMEDIATE
-                    let entry = DictEntry {
-                        body: current_def_body.clone(),
-                        immediate: false,
-                    };
-                    dictionary.insert(name.clone(), entry);
-                    latest_word = Some(name);
-
-                    current_def_body.clear();
-                    compiling = false;
-                    loop_depth = 0; // Reset for next potential definition
+            Token::Semicolon => return Err(ParseError::UnexpectedToken(Token::Semicolon)),
+            Token::Word(s) => {
+                // Check for control words used outside definition
+                if CONTROL_WORDS.contains(&s.to_lowercase().as_str()) {
+                    return Err(ParseError::ControlWordOutsideDefinition(s));
                 }
-                Token::Colon => return Err(ParseError::NestedDefinitionNotSupported),
-                Token::Word(s) => {
-                    let lower_s = s.to_lowercase();
-
-                    // Check if this is an immediate word
-                    let upper_s = s.to_uppercase();
-                    if let Some(entry) = dictionary.get(&upper_s) {
-                        if entry.immediate {
-                            // Clone the body to avoid borrow issues
-                            let immediate_body = entry.body.clone();
-                            // Execute immediate word directly during compilation
-                            if let Err(e) = eval(
-                                &immediate_body,
-                                &mut stack,
-                                &mut dictionary,
-                                &mut loop_control_stack,
-                                &mut latest_word,
-                            ) {
-                                // Convert EvalError to ParseError
-                                return Err(ParseError::ImmediateWordError(format!(
-                                    "{}: {}",
-                                    s, e
-                                )));
-                            }
-                            continue; // Skip adding to definition
-                        }
-                    }
-
-                    match lower_s.as_str() {
-                        "do" => {
-                            current_def_body.push(ForthOp::Do);
-                            loop_depth += 1;
-                        }
-                        "loop" => {
-                            if loop_depth == 0 {
-                                return Err(ParseError::MismatchedDoLoop);
-                            }
-                            current_def_body.push(ForthOp::Loop);
-                            loop_depth -= 1;
-                        }
-                        "i" => {
-                            // 'i' is only meaningful inside a loop, but we parse it anyway.
-                            // Runtime check will happen in eval.
-                            current_def_body.push(ForthOp::I);
-                        }
-                        "immediate" => {
-                            // IMMEDIATE is handled separately during execution,
-                            // but we still add it to the definition
-                            current_def_body.push(ForthOp::Immediate);
-                        }
-                        // Handle other words normally within definition
-                        _ => {
-                            if let Some(op) = parse_token_to_op(Token::Word(s.clone())) {
-                                current_def_body.push(op);
-                            } else {
-                                // This case should ideally not be reached if parse_token_to_op handles ForthOp::Word
-                                return Err(ParseError::UnexpectedToken(Token::Word(s)));
-                            }
-                        }
-                    }
+                if let Some(op) = parse_named_word(&s, token_iter, false)? {
+                    return Ok(Some(op));
                 }
-                // Handle numbers and other potential tokens within definition
-                _ => {
-                    if let Some(op) = parse_token_to_op(token.clone()) {
-                        current_def_body.push(op);
-                    } else {
-                        return Err(ParseError::UnexpectedToken(token));
-                    }
+
+                // Regular word, including IMMEDIATE which marks vm.latest_word when run
+                let at = vm.token_location(token_iter.len());
+                return word_op(&s, at, vm).map(Some);
+            }
+            // Handle numbers etc. outside definition
+            other => {
+                if let Some(op) = string_op(&other, false, vm)? {
+                    return Ok(Some(op));
                 }
+                return match parse_token_to_op(other.clone()) {
+                    Some(op) => Ok(Some(op)),
+                    None => Err(ParseError::UnexpectedToken(other)),
+                };
             }
-        } else {
-            // Interpret mode: compile-only words treated as data
-            match token {
-                Token::Colon => {
-                    // Start new definition
-                    match token_iter.next() {
-                        Some(Token::Word(name)) => {
-                            compiling = true;
-                            current_def_name = Some(name.to_uppercase());
-                        }
-                        _ => return Err(ParseError::ExpectedWordName),
-                    }
+        }
+    }
+    Ok(None)
+}
+
+// Compile more of the definition open in vm.compiler, up to and including
+// its closing ; which gives its Define op. Immediate words found in the VM's
+// dictionary are executed right away, each once even when the definition
+// spans several lines. Running out of tokens leaves the definition open;
+// any other error drops it.
+pub fn continue_definition(
+    token_iter: &mut vec::IntoIter<Token>,
+    vm: &mut Forth,
+) -> Result<ForthOp, ParseError> {
+    let result = compile_definition(token_iter, vm);
+    if !matches!(result, Err(ParseError::UnterminatedDefinition)) {
+        close_definition(vm);
+    }
+    result
+}
+
+// Drop the open definition, if any, and go back to interpreting
+pub(crate) fn close_definition(vm: &mut Forth) {
+    vm.compiler = None;
+    vm.set_state(false);
+}
+
+fn compile_definition(
+    token_iter: &mut vec::IntoIter<Token>,
+    vm: &mut Forth,
+) -> Result<ForthOp, ParseError> {
+    loop {
+        // Between [ and ] words are run as they are read, like at the prompt
+        if vm.compiler.as_ref().is_some_and(|c| c.interpreting) {
+            if let Some(Token::Word(s)) = token_iter.as_slice().first()
+                && s == "]"
+            {
+                token_iter.next();
+                with_compiler(vm, |compiler, vm| {
+                    compile_word("]", token_iter, compiler, vm)
+                })?;
+                continue;
+            }
+            let Some(op) = parse_next(token_iter, vm)? else {
+                break;
+            };
+            with_input(token_iter, vm, |vm| eval(std::slice::from_ref(&op), vm))
+                .map_err(|e| ParseError::ImmediateWordError(format!("{}: {}", op, e)))?;
+            continue;
+        }
+
+        let Some(token) = token_iter.next() else {
+            break;
+        };
+        match token {
+            // Skip whitespace and comments
+            Token::Whitespace | Token::Comment | Token::LineComment => {}
+            Token::Semicolon => {
+                let mut compiler = vm.compiler.take().unwrap_or_default();
+                // Every control structure must be closed before the end of the definition
+                if let Some(frame) = compiler.control_stack.last() {
+                    return Err(unclosed_error(frame));
                 }
-                Token::Semicolon => return Err(ParseError::UnexpectedToken(Token::Semicolon)),
-                Token::Word(s) => {
-                    // Check for control words used outside definition
-                    let lower_s = s.to_lowercase();
-                    if lower_s == "do"
-                        || lower_s == "loop"
-                        || lower_s == "i"
-                        || lower_s == "if"
-                        || lower_s == "else"
-                        || lower_s == "then"
-                    {
-                        return Err(ParseError::ControlWordOutsideDefinition(s));
-                    }
-
-                    // Handle IMMEDIATE in interpret mode
-                    if lower_s == "immediate" {
-                        // We'll allow IMMEDIATE without reporting an error
-                        // since latest_word will be checked during evaluation
-                        ops.push(ForthOp::Immediate);
-                        continue;
-                    }
-
-                    // Regular word or number
-                    if let Some(op) = parse_token_to_op(Token::Word(s.clone())) {
-                        ops.push(op);
-                    } else {
-                        // This case should ideally not be reached
-                        return Err(ParseError::UnexpectedToken(Token::Word(s)));
-                    }
+                // The code after each DOES> becomes the operand of a Does op
+                let mut body = compiler.body;
+                while let Some(mut part) = compiler.does_parts.pop() {
+                    part.push(ForthOp::Does(body));
+                    body = part;
                 }
-                // Handle numbers etc. outside definition
-                other => {
-                    if let Some(op) = parse_token_to_op(other.clone()) {
-                        ops.push(op);
-                    } else {
-                        return Err(ParseError::UnexpectedToken(other));
-                    }
+                return Ok(ForthOp::Define(compiler.name, body, false));
+            }
+            Token::Colon => return Err(ParseError::NestedDefinitionNotSupported),
+            Token::Word(s) => {
+                // Handle immediate words during compilation
+                if let Some(xt) = vm.lookup_xt(&s.to_uppercase())
+                    && vm.entries[xt].immediate
+                {
+                    // Execute immediate word against the VM's own stack and
+                    // dictionary. It may parse names from the rest of the source.
+                    with_input(token_iter, vm, |vm| execute(xt, vm))
+                        .map_err(|e| ParseError::ImmediateWordError(format!("{}: {}", s, e)))?;
+                } else {
+                    with_compiler(vm, |compiler, vm| {
+                        compile_word(&s, token_iter, compiler, vm)
+                    })?;
                 }
             }
+            // .( prints while the definition is compiled
+            Token::DotParen(text) => io_ops::write_bytes(&mut io::stdout(), text.as_bytes())
+                .map_err(|e| ParseError::ImmediateWordError(format!(".(: {}", e)))?,
+            // Handle numbers and other potential tokens within definition
+            _ => match string_op(&token, true, vm)?.or_else(|| parse_token_to_op(token.clone())) {
+                Some(op) => with_compiler(vm, |compiler, _| compiler.current_body().push(op)),
+                None => return Err(ParseError::UnexpectedToken(token)),
+            },
         }
     }
 
-    // Check if we ended mid-definition or with unbalanced loops
-    if compiling {
-        // Change the error priority - UnterminatedDefinition takes precedence
-        return Err(ParseError::UnterminatedDefinition);
-        // The MismatchedDoLoop check becomes unreachable now,
-        // as UnterminatedDefinition error is returned first
+    // Reached the end of the input inside the definition
+    Err(ParseError::UnterminatedDefinition)
+}
+
+// Run `f` with the token stream as the VM's input, so words can parse names
+// from the rest of the source being compiled
+fn with_input<T>(
+    token_iter: &mut vec::IntoIter<Token>,
+    vm: &mut Forth,
+    f: impl FnOnce(&mut Forth) -> T,
+) -> T {
+    vm.input = std::mem::take(token_iter);
+    let result = f(vm);
+    *token_iter = std::mem::take(&mut vm.input);
+    result
+}
+
+// Run `f` on the definition being compiled, lifted out of the VM for the call
+pub(crate) fn with_compiler<T>(
+    vm: &mut Forth,
+    f: impl FnOnce(&mut Compiler, &mut Forth) -> T,
+) -> T {
+    let mut compiler = vm.compiler.take().unwrap_or_default();
+    let result = f(&mut compiler, vm);
+    vm.compiler = Some(compiler);
+    result
+}
+
+// True for words the compiler knows without a dictionary entry
+fn is_builtin(word: &str) -> bool {
+    CONTROL_WORDS.contains(&word.to_lowercase().as_str())
+        || primitive_op(word).is_some()
+        || !matches!(
+            parse_named_word(word, &mut Vec::new().into_iter(), true),
+            Ok(None)
+        )
+}
+
+// Compile one word into the current definition. Control words open and close
+// structures on the control stack; anything else becomes an op. This is also
+// what a POSTPONEd built-in does when the word containing it runs.
+pub(crate) fn compile_word(
+    word: &str,
+    token_iter: &mut vec::IntoIter<Token>,
+    compiler: &mut Compiler,
+    vm: &mut Forth,
+) -> Result<(), ParseError> {
+    let lower_s = word.to_lowercase();
+    let control_stack = &mut compiler.control_stack;
+    match lower_s.as_str() {
+        "if" => control_stack.push(ControlFrame::If(Vec::new())),
+        "else" => match control_stack.pop() {
+            Some(ControlFrame::If(then_ops)) => {
+                control_stack.push(ControlFrame::Else(then_ops, Vec::new()))
+            }
+            _ => return Err(ParseError::MismatchedConditional),
+        },
+        "then" => {
+            let op = match control_stack.pop() {
+                Some(ControlFrame::If(then_ops)) => ForthOp::IfElse(then_ops, Vec::new()),
+                Some(ControlFrame::Else(then_ops, else_ops)) => ForthOp::IfElse(then_ops, else_ops),
+                _ => return Err(ParseError::MismatchedConditional),
+            };
+            compiler.current_body().push(op);
+        }
+        "do" => control_stack.push(ControlFrame::Do(ForthOp::Do, Vec::new())),
+        "?do" => control_stack.push(ControlFrame::Do(ForthOp::QDo, Vec::new())),
+        "loop" | "+loop" => {
+            let Some(ControlFrame::Do(do_op, loop_body)) = control_stack.pop() else {
+                return Err(ParseError::MismatchedDoLoop);
+            };
+            // DO ... LOOP stays flat in the enclosing body
+            let body = compiler.current_body();
+            body.push(do_op);
+            body.extend(loop_body);
+            body.push(if lower_s == "loop" {
+                ForthOp::Loop
+            } else {
+                ForthOp::PlusLoop
+            });
+        }
+        "j" | "k" | "leave" | "unloop" => {
+            let (op, needed) = match lower_s.as_str() {
+                "j" => (ForthOp::J, 2),
+                "k" => (ForthOp::K, 3),
+                "leave" => (ForthOp::Leave, 1),
+                _ => (ForthOp::Unloop, 1),
+            };
+            let loop_depth = control_stack
+                .iter()
+                .filter(|frame| matches!(frame, ControlFrame::Do(..)))
+                .count();
+            if loop_depth < needed {
+                return Err(ParseError::MismatchedDoLoop);
+            }
+            compiler.current_body().push(op);
+        }
+        "begin" => control_stack.push(ControlFrame::Begin(Vec::new())),
+        "while" => match control_stack.pop() {
+            Some(ControlFrame::Begin(cond)) => {
+                control_stack.push(ControlFrame::While(cond, Vec::new()))
+            }
+            _ => return Err(ParseError::MismatchedBeginLoop),
+        },
+        "until" | "again" | "repeat" => {
+            let op = match (lower_s.as_str(), control_stack.pop()) {
+                ("until", Some(ControlFrame::Begin(body))) => ForthOp::BeginUntil(body),
+                ("again", Some(ControlFrame::Begin(body))) => ForthOp::BeginAgain(body),
+                ("repeat", Some(ControlFrame::While(cond, body))) => {
+                    ForthOp::BeginWhileRepeat(cond, body)
+                }
+                _ => return Err(ParseError::MismatchedBeginLoop),
+            };
+            compiler.current_body().push(op);
+        }
+        "case" => control_stack.push(ControlFrame::Case(Vec::new(), Vec::new())),
+        "of" => match control_stack.pop() {
+            Some(ControlFrame::Case(clauses, value)) => {
+                control_stack.push(ControlFrame::Of(clauses, value, Vec::new()))
+            }
+            _ => return Err(ParseError::MismatchedCase),
+        },
+        "endof" => match control_stack.pop() {
+            Some(ControlFrame::Of(mut clauses, value, body)) => {
+                clauses.push((value, body));
+                control_stack.push(ControlFrame::Case(clauses, Vec::new()))
+            }
+            _ => return Err(ParseError::MismatchedCase),
+        },
+        "endcase" => {
+            let Some(ControlFrame::Case(clauses, default)) = control_stack.pop() else {
+                return Err(ParseError::MismatchedCase);
+            };
+            compiler
+                .current_body()
+                .push(ForthOp::Case(clauses, default));
+        }
+        "[']" => {
+            // Compile the execution token of an existing word as a literal
+            let Some(name) = word_name(token_iter.next()) else {
+                return Err(ParseError::ExpectedWordName);
+            };
+            let Some(xt) = vm.find_xt(&name.to_uppercase()) else {
+                return Err(ParseError::UnknownWord(name));
+            };
+            compiler.current_body().push(ForthOp::Push(xt as i64));
+        }
+        "does>" => {
+            // DOES> ends the defining part of the word, so it cannot
+            // sit inside a control structure
+            if let Some(frame) = control_stack.last() {
+                return Err(unclosed_error(frame));
+            }
+            let part = std::mem::take(&mut compiler.body);
+            compiler.does_parts.push(part);
+        }
+        "exit" => compiler.current_body().push(ForthOp::Exit),
+        // Refers to this definition even if an older word has the same name
+        "recurse" => {
+            let at = vm.token_location(token_iter.len());
+            compiler.current_body().push(ForthOp::Recurse(at));
+        }
+        "i" => {
+            // 'i' is only meaningful inside a loop, but we parse it anyway.
+            // Runtime check will happen in eval.
+            compiler.current_body().push(ForthOp::I);
+        }
+        "immediate" => {
+            // IMMEDIATE is handled separately during execution,
+            // but we still add it to the definition
+            compiler.current_body().push(ForthOp::Immediate);
+        }
+        // Switch to interpreting until ], and back
+        "[" => {
+            compiler.interpreting = true;
+            vm.set_state(false);
+        }
+        "]" => {
+            compiler.interpreting = false;
+            vm.set_state(true);
+        }
+        "literal" => {
+            // Compile the number on top of the stack
+            let x = vm
+                .pop()
+                .map_err(|e| ParseError::ImmediateWordError(format!("LITERAL: {}", e)))?;
+            compiler.current_body().push(ForthOp::Push(x));
+        }
+        "postpone" => {
+            let Some(name) = word_name(token_iter.next()) else {
+                return Err(ParseError::ExpectedWordName);
+            };
+            let upper_name = name.to_uppercase();
+            let user_xt = vm.dictionary.get(&upper_name).copied();
+            let ops = match user_xt {
+                // An immediate word runs when the word being defined runs
+                Some(xt) if vm.entries[xt].immediate => {
+                    vec![ForthOp::Push(xt as i64), ForthOp::Execute]
+                }
+                // Built-ins are compiled by the compiler itself, then
+                _ if is_builtin(&name) => vec![ForthOp::Postpone(upper_name)],
+                // Other words are compiled into whatever definition is open then
+                Some(xt) => vec![ForthOp::Push(xt as i64), ForthOp::CompileComma],
+                None => return Err(ParseError::UnknownWord(name)),
+            };
+            compiler.current_body().extend(ops);
+        }
+        // Handle other words normally within definition
+        _ => {
+            if let Some(op) = parse_named_word(word, token_iter, true)? {
+                compiler.current_body().push(op);
+            } else {
+                // The word was the last token read, except for a POSTPONEd
+                // word, which then gets the location of the word compiling it
+                let at = vm.token_location(token_iter.len());
+                let op = word_op(word, at, vm)?;
+                compiler.current_body().push(op);
+            }
+        }
+    }
+    Ok(())
+}
+
+// The error for a control structure still open at ; or DOES>
+fn unclosed_error(frame: &ControlFrame) -> ParseError {
+    match frame {
+        ControlFrame::If(_) | ControlFrame::Else(..) => ParseError::UnterminatedConditional,
+        ControlFrame::Do(..) => ParseError::MismatchedDoLoop,
+        ControlFrame::Begin(_) | ControlFrame::While(..) => ParseError::MismatchedBeginLoop,
+        ControlFrame::Case(..) | ControlFrame::Of(..) => ParseError::MismatchedCase,
     }
+}
 
-    Ok(ops)
+impl Compiler {
+    // The op list currently being compiled: the innermost open control
+    // structure, or the definition body itself
+    pub(crate) fn current_body(&mut self) -> &mut Vec<ForthOp> {
+        match self.control_stack.last_mut() {
+            None => &mut self.body,
+            Some(
+                ControlFrame::If(body)
+                | ControlFrame::Else(_, body)
+                | ControlFrame::Do(_, body)
+                | ControlFrame::Begin(body)
+                | ControlFrame::While(_, body)
+                | ControlFrame::Case(_, body)
+                | ControlFrame::Of(_, _, body),
+            ) => body,
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::token::Token;
+    use logos::Logos;
 
     // ... existing test_parse_basic_ops ...
     #[test]
     fn test_parse_basic_ops() {
         let tokens = vec![
-            Token::Integer(10),
-            Token::Integer(5),
+            Token::Word("10".to_string()),
+            Token::Word("5".to_string()),
             Token::Word("+".to_string()),
             Token::Word(".".to_string()),
         ];
@@ -403,7 +1088,7 @@ mod tests {
             ForthOp::Add,
             ForthOp::Print,
         ]);
-        assert_eq!(parse(tokens), expected_ops);
+        assert_eq!(parse(tokens, &mut Forth::new()), expected_ops);
     }
 
     // ... existing test_parse_stack_ops ...
@@ -435,15 +1120,15 @@ mod tests {
             ForthOp::TwoOver,
             ForthOp::MinusRot,
         ]);
-        assert_eq!(parse(tokens), expected_ops);
+        assert_eq!(parse(tokens, &mut Forth::new()), expected_ops);
     }
 
     // ... existing test_parse_all_known_words ...
     #[test]
     fn test_parse_all_known_words() {
         let tokens = vec![
-            Token::Integer(1),
-            Token::Integer(2),
+            Token::Word("1".to_string()),
+            Token::Word("2".to_string()),
             Token::Word("+".to_string()),
             Token::Word("-".to_string()),
             Token::Word("*".to_string()),
@@ -473,9 +1158,9 @@ mod tests {
             ForthOp::Swap,
             ForthOp::Over,
             ForthOp::Rot,
-            ForthOp::Word("unknown".to_string()), // Unknown words are passed through
+            ForthOp::Word("unknown".to_string(), None), // Unknown words are passed through
         ]);
-        assert_eq!(parse(tokens), expected_ops);
+        assert_eq!(parse(tokens, &mut Forth::new()), expected_ops);
     }
 
     // ... existing test_parse_empty ...
@@ -483,7 +1168,7 @@ mod tests {
     fn test_parse_empty() {
         let tokens = vec![];
         let expected_ops = Ok(vec![]);
-        assert_eq!(parse(tokens), expected_ops);
+        assert_eq!(parse(tokens, &mut Forth::new()), expected_ops);
     }
 
     #[test]
@@ -491,7 +1176,7 @@ mod tests {
         let tokens = vec![
             Token::Colon,
             Token::Word("DOUBLE".to_string()),
-            Token::Integer(2),
+            Token::Word("2".to_string()),
             Token::Word("*".to_string()),
             Token::Semicolon,
         ];
@@ -500,13 +1185,13 @@ mod tests {
             vec![ForthOp::Push(2), ForthOp::Multiply],
             false,
         )]);
-        assert_eq!(parse(tokens), expected_ops);
+        assert_eq!(parse(tokens, &mut Forth::new()), expected_ops);
     }
 
     #[test]
     fn test_parse_mixed_definition_and_execution() {
         let tokens = vec![
-            Token::Integer(10),
+            Token::Word("10".to_string()),
             Token::Colon,
             Token::Word("SQUARE".to_string()),
             Token::Word("DUP".to_string()),
@@ -522,10 +1207,10 @@ mod tests {
                 vec![ForthOp::Dup, ForthOp::Multiply],
                 false,
             ),
-            ForthOp::Word("SQUARE".to_string()),
+            ForthOp::Word("SQUARE".to_string(), None),
             ForthOp::Print,
         ]);
-        assert_eq!(parse(tokens), expected_ops);
+        assert_eq!(parse(tokens, &mut Forth::new()), expected_ops);
     }
 
     #[test]
@@ -533,16 +1218,19 @@ mod tests {
         let tokens = vec![
             Token::Colon,
             Token::Word("TEST".to_string()),
-            Token::Integer(1),
+            Token::Word("1".to_string()),
         ];
-        assert_eq!(parse(tokens), Err(ParseError::UnterminatedDefinition));
+        assert_eq!(
+            parse(tokens, &mut Forth::new()),
+            Err(ParseError::UnterminatedDefinition)
+        );
     }
 
     #[test]
     fn test_parse_error_unexpected_semicolon() {
-        let tokens = vec![Token::Integer(1), Token::Semicolon];
+        let tokens = vec![Token::Word("1".to_string()), Token::Semicolon];
         assert_eq!(
-            parse(tokens),
+            parse(tokens, &mut Forth::new()),
             Err(ParseError::UnexpectedToken(Token::Semicolon))
         );
     }
@@ -551,15 +1239,21 @@ mod tests {
     fn test_parse_error_colon_no_name() {
         let tokens = vec![
             Token::Colon,
-            Token::Integer(5), // Not a word name
+            Token::Word("5".to_string()), // Not a word name
         ];
-        assert_eq!(parse(tokens), Err(ParseError::ExpectedWordName));
+        assert_eq!(
+            parse(tokens, &mut Forth::new()),
+            Err(ParseError::ExpectedWordName)
+        );
     }
 
     #[test]
     fn test_parse_error_colon_eof() {
         let tokens = vec![Token::Colon];
-        assert_eq!(parse(tokens), Err(ParseError::ExpectedWordName));
+        assert_eq!(
+            parse(tokens, &mut Forth::new()),
+            Err(ParseError::ExpectedWordName)
+        );
     }
 
     #[test]
@@ -572,19 +1266,22 @@ mod tests {
             Token::Semicolon,
             Token::Semicolon,
         ];
-        assert_eq!(parse(tokens), Err(ParseError::NestedDefinitionNotSupported));
+        assert_eq!(
+            parse(tokens, &mut Forth::new()),
+            Err(ParseError::NestedDefinitionNotSupported)
+        );
     }
 
     #[test]
     fn test_parse_if_then() {
         let tokens = vec![
-            Token::Integer(1),
+            Token::Word("1".to_string()),
             Token::Word("if".to_string()), // Should cause error
             Token::Word("dup".to_string()),
             Token::Word("then".to_string()),
         ];
         assert_eq!(
-            parse(tokens),
+            parse(tokens, &mut Forth::new()),
             Err(ParseError::ControlWordOutsideDefinition("if".to_string()))
         );
     }
@@ -592,7 +1289,7 @@ mod tests {
     #[test]
     fn test_parse_if_else_then() {
         let tokens = vec![
-            Token::Integer(0),
+            Token::Word("0".to_string()),
             Token::Word("if".to_string()), // Should cause error
             Token::Word("dup".to_string()),
             Token::Word("else".to_string()),
@@ -600,7 +1297,7 @@ mod tests {
             Token::Word("then".to_string()),
         ];
         assert_eq!(
-            parse(tokens),
+            parse(tokens, &mut Forth::new()),
             Err(ParseError::ControlWordOutsideDefinition("if".to_string()))
         );
     }
@@ -615,7 +1312,7 @@ mod tests {
             Token::Word("then".to_string()),
         ];
         assert_eq!(
-            parse(tokens),
+            parse(tokens, &mut Forth::new()),
             Err(ParseError::ControlWordOutsideDefinition("if".to_string()))
         );
     }
@@ -626,8 +1323,8 @@ mod tests {
         let tokens = vec![
             Token::Colon,
             Token::Word("TEST".to_string()),
-            Token::Integer(10),
-            Token::Integer(0),
+            Token::Word("10".to_string()),
+            Token::Word("0".to_string()),
             Token::Word("DO".to_string()),
             Token::Word("I".to_string()),
             Token::Word(".".to_string()),
@@ -647,7 +1344,7 @@ mod tests {
             expected_body,
             false,
         )]);
-        assert_eq!(parse(tokens), expected_ops);
+        assert_eq!(parse(tokens, &mut Forth::new()), expected_ops);
     }
 
     #[test]
@@ -659,7 +1356,10 @@ mod tests {
             Token::Word("LOOP".to_string()),
             Token::Semicolon,
         ];
-        assert_eq!(parse(tokens), Err(ParseError::MismatchedDoLoop));
+        assert_eq!(
+            parse(tokens, &mut Forth::new()),
+            Err(ParseError::MismatchedDoLoop)
+        );
     }
 
     #[test]
@@ -671,7 +1371,10 @@ mod tests {
             Token::Word("DO".to_string()),
             Token::Semicolon,
         ];
-        assert_eq!(parse(tokens), Err(ParseError::MismatchedDoLoop)); // Error detected at Semicolon
+        assert_eq!(
+            parse(tokens, &mut Forth::new()),
+            Err(ParseError::MismatchedDoLoop)
+        ); // Error detected at Semicolon
     }
 
     #[test]
@@ -683,7 +1386,10 @@ mod tests {
             Token::Word("DO".to_string()),
         ];
         // Error detected at EOF check
-        assert_eq!(parse(tokens), Err(ParseError::UnterminatedDefinition));
+        assert_eq!(
+            parse(tokens, &mut Forth::new()),
+            Err(ParseError::UnterminatedDefinition)
+        );
         // A more specific error might be better, but this works for now.
         // If we refine EOF checking, it could become MismatchedDoLoop.
     }
@@ -692,20 +1398,606 @@ mod tests {
     fn test_parse_error_control_word_outside_definition() {
         let tokens_do = vec![Token::Word("do".to_string())];
         assert_eq!(
-            parse(tokens_do),
+            parse(tokens_do, &mut Forth::new()),
             Err(ParseError::ControlWordOutsideDefinition("do".to_string()))
         );
 
         let tokens_loop = vec![Token::Word("loop".to_string())];
         assert_eq!(
-            parse(tokens_loop),
+            parse(tokens_loop, &mut Forth::new()),
             Err(ParseError::ControlWordOutsideDefinition("loop".to_string()))
         );
 
         let tokens_i = vec![Token::Word("i".to_string())];
         assert_eq!(
-            parse(tokens_i),
+            parse(tokens_i, &mut Forth::new()),
             Err(ParseError::ControlWordOutsideDefinition("i".to_string()))
         );
     }
+
+    // Lex and parse source text, for the longer control-structure tests
+    fn parse_source(code: &str) -> Result<Vec<ForthOp>, ParseError> {
+        let tokens: Vec<Token> = Token::lexer(code).filter_map(|r| r.ok()).collect();
+        parse(tokens, &mut Forth::new())
+    }
+
+    #[test]
+    fn test_parse_if_inside_definition() {
+        let expected_ops = Ok(vec![ForthOp::Define(
+            "TEST".to_string(),
+            vec![
+                ForthOp::Dup,
+                ForthOp::IfElse(
+                    vec![ForthOp::IfElse(vec![ForthOp::Push(1)], vec![])],
+                    vec![ForthOp::Push(2)],
+                ),
+            ],
+            false,
+        )]);
+        assert_eq!(
+            parse_source(": TEST dup if if 1 then else 2 then ;"),
+            expected_ops
+        );
+    }
+
+    #[test]
+    fn test_parse_loop_inside_if() {
+        let expected_ops = Ok(vec![ForthOp::Define(
+            "TEST".to_string(),
+            vec![ForthOp::IfElse(
+                vec![
+                    ForthOp::Push(3),
+                    ForthOp::Push(0),
+                    ForthOp::Do,
+                    ForthOp::I,
+                    ForthOp::Loop,
+                ],
+                vec![],
+            )],
+            false,
+        )]);
+        assert_eq!(parse_source(": TEST if 3 0 do i loop then ;"), expected_ops);
+    }
+
+    #[test]
+    fn test_parse_begin_loops() {
+        let expected_ops = Ok(vec![ForthOp::Define(
+            "TEST".to_string(),
+            vec![
+                ForthOp::BeginUntil(vec![ForthOp::Push(1), ForthOp::Subtract, ForthOp::Dup]),
+                ForthOp::BeginWhileRepeat(
+                    vec![ForthOp::Dup],
+                    vec![ForthOp::Push(1), ForthOp::Subtract],
+                ),
+                ForthOp::BeginAgain(vec![ForthOp::Word("step".to_string(), None)]),
+            ],
+            false,
+        )]);
+        assert_eq!(
+            parse_source(
+                ": TEST begin 1 - dup until begin dup while 1 - repeat begin step again ;"
+            ),
+            expected_ops
+        );
+    }
+
+    #[test]
+    fn test_parse_nested_begin_and_do() {
+        let expected_ops = Ok(vec![ForthOp::Define(
+            "TEST".to_string(),
+            vec![ForthOp::BeginUntil(vec![
+                ForthOp::Push(2),
+                ForthOp::Push(0),
+                ForthOp::Do,
+                ForthOp::BeginUntil(vec![ForthOp::I]),
+                ForthOp::Loop,
+                ForthOp::Push(-1),
+            ])],
+            false,
+        )]);
+        assert_eq!(
+            parse_source(": TEST begin 2 0 do begin i until loop -1 until ;"),
+            expected_ops
+        );
+    }
+
+    #[test]
+    fn test_parse_case() {
+        let expected_ops = Ok(vec![ForthOp::Define(
+            "TEST".to_string(),
+            vec![ForthOp::Case(
+                vec![
+                    (vec![ForthOp::Push(1)], vec![ForthOp::Push(10)]),
+                    (
+                        vec![ForthOp::Push(1), ForthOp::Push(1), ForthOp::Add],
+                        vec![ForthOp::IfElse(vec![ForthOp::Push(20)], vec![])],
+                    ),
+                ],
+                vec![ForthOp::Dup],
+            )],
+            false,
+        )]);
+        assert_eq!(
+            parse_source(": TEST case 1 of 10 endof 1 1 + of if 20 then endof dup endcase ;"),
+            expected_ops
+        );
+    }
+
+    #[test]
+    fn test_parse_error_mismatched_case() {
+        for code in [
+            ": TEST 1 of 2 endof ;",
+            ": TEST case 1 endof endcase ;",
+            ": TEST case 1 of 2 endcase ;",
+            ": TEST endcase ;",
+            ": TEST case 1 of 2 endof ;",
+        ] {
+            assert_eq!(
+                parse_source(code),
+                Err(ParseError::MismatchedCase),
+                "{}",
+                code
+            );
+        }
+        // CASE must be closed inside the IF it was opened in
+        assert_eq!(
+            parse_source(": TEST if case then endcase ;"),
+            Err(ParseError::MismatchedConditional)
+        );
+        assert_eq!(
+            parse_source("1 of"),
+            Err(ParseError::ControlWordOutsideDefinition("of".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_parse_defining_words() {
+        let expected_ops = Ok(vec![
+            ForthOp::Variable(Some("X".to_string())),
+            ForthOp::TwoVariable(Some("P".to_string())),
+            ForthOp::Push(1),
+            ForthOp::Constant(Some("ONE".to_string())),
+            ForthOp::Push(1),
+            ForthOp::Push(2),
+            ForthOp::TwoConstant(Some("PAIR".to_string())),
+            ForthOp::Push(0),
+            ForthOp::Value(Some("V".to_string())),
+            ForthOp::Define(
+                "SET".to_string(),
+                vec![ForthOp::To("V".to_string()), ForthOp::Dup],
+                false,
+            ),
+        ]);
+        assert_eq!(
+            parse_source(
+                "variable x 2variable p 1 constant one 1 2 2constant pair 0 value v : SET to v dup ;"
+            ),
+            expected_ops
+        );
+    }
+
+    #[test]
+    fn test_parse_create_does() {
+        let expected_ops = Ok(vec![ForthOp::Define(
+            "CONST".to_string(),
+            vec![
+                ForthOp::Create(None),
+                ForthOp::Comma,
+                ForthOp::Does(vec![ForthOp::Fetch]),
+            ],
+            false,
+        )]);
+        assert_eq!(parse_source(": CONST create , does> @ ;"), expected_ops);
+        // Each DOES> nests the code that follows it
+        let expected_ops = Ok(vec![ForthOp::Define(
+            "TWICE".to_string(),
+            vec![
+                ForthOp::Push(1),
+                ForthOp::Does(vec![
+                    ForthOp::Push(2),
+                    ForthOp::Does(vec![ForthOp::Push(3)]),
+                ]),
+            ],
+            false,
+        )]);
+        assert_eq!(parse_source(": TWICE 1 does> 2 does> 3 ;"), expected_ops);
+        assert_eq!(
+            parse_source("create BUF 8 allot"),
+            Ok(vec![
+                ForthOp::Create(Some("BUF".to_string())),
+                ForthOp::Push(8),
+                ForthOp::Allot
+            ])
+        );
+    }
+
+    #[test]
+    fn test_parse_error_does() {
+        assert_eq!(
+            parse_source(": TEST create if does> then ;"),
+            Err(ParseError::UnterminatedConditional)
+        );
+        assert_eq!(
+            parse_source("does> 1"),
+            Err(ParseError::ControlWordOutsideDefinition(
+                "does>".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_return_stack_words() {
+        let expected_ops = Ok(vec![ForthOp::Define(
+            "TEST".to_string(),
+            vec![
+                ForthOp::ToR,
+                ForthOp::RFrom,
+                ForthOp::RFetch,
+                ForthOp::TwoToR,
+                ForthOp::TwoRFrom,
+                ForthOp::TwoRFetch,
+                ForthOp::RDrop,
+            ],
+            false,
+        )]);
+        assert_eq!(
+            parse_source(": TEST >r R> r@ 2>r 2R> 2r@ rdrop ;"),
+            expected_ops
+        );
+        assert_eq!(
+            parse_source("1 >r"),
+            Err(ParseError::ControlWordOutsideDefinition(">r".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_parse_execution_tokens() {
+        let mut vm = Forth::new();
+        vm.interpret(": SQUARE dup * ;").unwrap();
+        let xt = *vm.dictionary.get("SQUARE").unwrap() as i64;
+        let tokens =
+            Token::lexer("' square defer D : TEST ['] SQUARE ' is D action-of d execute ;")
+                .filter_map(|r| r.ok())
+                .collect();
+        let expected_ops = Ok(vec![
+            ForthOp::Tick(Some("SQUARE".to_string())),
+            ForthOp::Defer(Some("D".to_string())),
+            ForthOp::Define(
+                "TEST".to_string(),
+                vec![
+                    ForthOp::Push(xt),
+                    ForthOp::Tick(None),
+                    ForthOp::Is("D".to_string()),
+                    ForthOp::ActionOf("D".to_string()),
+                    ForthOp::Execute,
+                ],
+                false,
+            ),
+        ]);
+        assert_eq!(parse(tokens, &mut vm), expected_ops);
+    }
+
+    #[test]
+    fn test_parse_resolved_calls() {
+        let mut vm = Forth::new();
+        vm.interpret(": SQUARE dup * ;").unwrap();
+        let xt = *vm.dictionary.get("SQUARE").unwrap();
+        // Known words are called by execution token, the rest by name
+        let tokens = Token::lexer(": QUAD square SQUARE later ; square")
+            .filter_map(|r| r.ok())
+            .collect();
+        let expected_ops = Ok(vec![
+            ForthOp::Define(
+                "QUAD".to_string(),
+                vec![
+                    ForthOp::Call(xt, None),
+                    ForthOp::Call(xt, None),
+                    ForthOp::Word("later".to_string(), None),
+                ],
+                false,
+            ),
+            ForthOp::Call(xt, None),
+        ]);
+        assert_eq!(parse(tokens, &mut vm), expected_ops);
+    }
+
+    #[test]
+    fn test_parse_error_bracket_tick() {
+        assert_eq!(
+            parse_source(": TEST ['] NOPE ;"),
+            Err(ParseError::UnknownWord("NOPE".to_string()))
+        );
+        assert_eq!(
+            parse_source(": TEST ['] ;"),
+            Err(ParseError::ExpectedWordName)
+        );
+        assert_eq!(
+            parse_source("['] dup"),
+            Err(ParseError::ControlWordOutsideDefinition("[']".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_parse_error_defining_word_without_name() {
+        for code in ["variable", "1 constant ;", "value 5", ": TEST to ;"] {
+            assert_eq!(
+                parse_source(code),
+                Err(ParseError::ExpectedWordName),
+                "{}",
+                code
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_error_mismatched_begin_loop() {
+        assert_eq!(
+            parse_source(": TEST 1 until ;"),
+            Err(ParseError::MismatchedBeginLoop)
+        );
+        assert_eq!(
+            parse_source(": TEST begin 1 repeat ;"),
+            Err(ParseError::MismatchedBeginLoop)
+        );
+        assert_eq!(
+            parse_source(": TEST while ;"),
+            Err(ParseError::MismatchedBeginLoop)
+        );
+        assert_eq!(
+            parse_source(": TEST begin dup while again ;"),
+            Err(ParseError::MismatchedBeginLoop)
+        );
+        // BEGIN left open at the end of the definition
+        assert_eq!(
+            parse_source(": TEST begin 1 ;"),
+            Err(ParseError::MismatchedBeginLoop)
+        );
+    }
+
+    #[test]
+    fn test_parse_error_interleaved_control_structures() {
+        // Structures must be closed in the reverse order they were opened
+        assert_eq!(
+            parse_source(": TEST begin if until then ;"),
+            Err(ParseError::MismatchedBeginLoop)
+        );
+        assert_eq!(
+            parse_source(": TEST 3 0 do if loop then ;"),
+            Err(ParseError::MismatchedDoLoop)
+        );
+        assert_eq!(
+            parse_source(": TEST if begin then until ;"),
+            Err(ParseError::MismatchedConditional)
+        );
+        assert_eq!(
+            parse_source(": TEST 1 then ;"),
+            Err(ParseError::MismatchedConditional)
+        );
+        assert_eq!(
+            parse_source(": TEST 1 if 2 ;"),
+            Err(ParseError::UnterminatedConditional)
+        );
+    }
+
+    #[test]
+    fn test_parse_error_begin_outside_definition() {
+        for word in ["begin", "until", "while", "repeat", "again"] {
+            assert_eq!(
+                parse_source(word),
+                Err(ParseError::ControlWordOutsideDefinition(word.to_string()))
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_counted_loop_family() {
+        let expected_ops = Ok(vec![ForthOp::Define(
+            "TEST".to_string(),
+            vec![
+                ForthOp::Push(10),
+                ForthOp::Push(0),
+                ForthOp::QDo,
+                ForthOp::Push(3),
+                ForthOp::Push(0),
+                ForthOp::Do,
+                ForthOp::J,
+                ForthOp::IfElse(vec![ForthOp::Leave], vec![]),
+                ForthOp::Push(2),
+                ForthOp::PlusLoop,
+                ForthOp::Loop,
+            ],
+            false,
+        )]);
+        assert_eq!(
+            parse_source(": TEST 10 0 ?do 3 0 do j if leave then 2 +loop loop ;"),
+            expected_ops
+        );
+    }
+
+    #[test]
+    fn test_parse_error_loop_words_outside_do() {
+        assert_eq!(
+            parse_source(": TEST leave ;"),
+            Err(ParseError::MismatchedDoLoop)
+        );
+        assert_eq!(
+            parse_source(": TEST unloop ;"),
+            Err(ParseError::MismatchedDoLoop)
+        );
+        assert_eq!(
+            parse_source(": TEST 1 +loop ;"),
+            Err(ParseError::MismatchedDoLoop)
+        );
+        // J needs two enclosing loops, K three
+        assert_eq!(
+            parse_source(": TEST 3 0 do j loop ;"),
+            Err(ParseError::MismatchedDoLoop)
+        );
+        assert_eq!(
+            parse_source(": TEST 3 0 do 3 0 do k loop loop ;"),
+            Err(ParseError::MismatchedDoLoop)
+        );
+        assert_eq!(
+            parse_source(": TEST 3 0 ?do begin i until ;"),
+            Err(ParseError::MismatchedDoLoop)
+        );
+        for word in ["?do", "+loop", "j", "k", "leave", "unloop"] {
+            assert_eq!(
+                parse_source(word),
+                Err(ParseError::ControlWordOutsideDefinition(word.to_string()))
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_exit_and_recurse() {
+        let expected_ops = Ok(vec![ForthOp::Define(
+            "TEST".to_string(),
+            vec![
+                ForthOp::Dup,
+                ForthOp::IfElse(vec![ForthOp::Exit], vec![]),
+                ForthOp::Recurse(None),
+            ],
+            false,
+        )]);
+        assert_eq!(
+            parse_source(": TEST dup if exit then recurse ;"),
+            expected_ops
+        );
+        for word in ["exit", "recurse"] {
+            assert_eq!(
+                parse_source(word),
+                Err(ParseError::ControlWordOutsideDefinition(word.to_string()))
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_brackets_and_literal() {
+        let expected_ops = Ok(vec![ForthOp::Define(
+            "FIVE".to_string(),
+            vec![ForthOp::Push(5), ForthOp::Dup],
+            false,
+        )]);
+        assert_eq!(parse_source(": FIVE [ 2 3 + ] literal dup ;"), expected_ops);
+        // STATE is false between [ and ], and true again after ]
+        let expected_ops = Ok(vec![ForthOp::Define(
+            "TEST".to_string(),
+            vec![ForthOp::Push(0), ForthOp::State],
+            false,
+        )]);
+        assert_eq!(
+            parse_source(": TEST [ state @ ] literal state ;"),
+            expected_ops
+        );
+        for word in ["[", "]", "literal", "postpone"] {
+            assert_eq!(
+                parse_source(word),
+                Err(ParseError::ControlWordOutsideDefinition(word.to_string()))
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_postpone() {
+        let mut vm = Forth::new();
+        let tokens = Token::lexer(": SQ dup * ; : NOW 1 ; immediate")
+            .filter_map(|r| r.ok())
+            .collect();
+        for op in parse(tokens, &mut vm).unwrap() {
+            crate::eval::eval(&[op], &mut vm).unwrap();
+        }
+        let sq = vm.dictionary["SQ"] as i64;
+        let now = vm.dictionary["NOW"] as i64;
+        let tokens = Token::lexer(": TEST postpone if postpone SQ postpone NOW ;")
+            .filter_map(|r| r.ok())
+            .collect();
+        let expected_ops = Ok(vec![ForthOp::Define(
+            "TEST".to_string(),
+            vec![
+                ForthOp::Postpone("IF".to_string()),
+                ForthOp::Push(sq),
+                ForthOp::CompileComma,
+                ForthOp::Push(now),
+                ForthOp::Execute,
+            ],
+            false,
+        )]);
+        assert_eq!(parse(tokens, &mut vm), expected_ops);
+    }
+
+    #[test]
+    fn test_parse_error_compiling_words() {
+        assert_eq!(
+            parse_source(": TEST postpone NOPE ;"),
+            Err(ParseError::UnknownWord("NOPE".to_string()))
+        );
+        assert_eq!(
+            parse_source(": TEST postpone ;"),
+            Err(ParseError::ExpectedWordName)
+        );
+        assert_eq!(
+            parse_source(": TEST literal ;"),
+            Err(ParseError::ImmediateWordError(
+                "LITERAL: Stack underflow".to_string()
+            ))
+        );
+        assert_eq!(
+            parse_source(": TEST [ : INNER ; ] ;"),
+            Err(ParseError::NestedDefinitionNotSupported)
+        );
+    }
+
+    #[test]
+    fn test_parse_strings() {
+        let mut vm = Forth::new();
+        let base = vm.memory().len() as i64;
+        let tokens = Token::lexer(r#": GREET ." Hi" s" abc" c" de" ; .( done)"#)
+            .filter_map(|r| r.ok())
+            .collect();
+        let expected_ops = Ok(vec![
+            ForthOp::Define(
+                "GREET".to_string(),
+                vec![
+                    ForthOp::PrintString("Hi".to_string()),
+                    ForthOp::SLiteral(base, 3),
+                    ForthOp::Push(base + 3),
+                ],
+                false,
+            ),
+            ForthOp::PrintString("done".to_string()),
+        ]);
+        assert_eq!(parse(tokens, &mut vm), expected_ops);
+        // The text was stored while compiling; C" adds a length byte
+        assert_eq!(&vm.memory()[base as usize..], b"abc\x02de");
+    }
+
+    #[test]
+    fn test_parse_exceptions() {
+        let tokens = Token::lexer(r#": T catch throw abort abort" no" ; abort" top""#)
+            .filter_map(|r| r.ok())
+            .collect();
+        let expected_ops = Ok(vec![
+            ForthOp::Define(
+                "T".to_string(),
+                vec![
+                    ForthOp::Catch,
+                    ForthOp::Throw,
+                    ForthOp::Abort,
+                    ForthOp::AbortQuote("no".to_string()),
+                ],
+                false,
+            ),
+            ForthOp::AbortQuote("top".to_string()),
+        ]);
+        assert_eq!(parse(tokens, &mut Forth::new()), expected_ops);
+    }
+
+    #[test]
+    fn test_parse_error_counted_string_too_long() {
+        let code = format!("c\" {}\"", "x".repeat(256));
+        assert_eq!(parse_source(&code), Err(ParseError::StringTooLong));
+        let code = format!(": TEST c\" {}\" ;", "x".repeat(255));
+        assert!(parse_source(&code).is_ok());
+    }
 }
@@ -12,7 +12,7 @@ macro_rules! check_depth {
 // ( n -- n n )
 pub fn dup(stack: &mut Vec<i64>) -> Result<(), EvalError> {
     check_depth!(stack, 1);
-    stack.push(stack.last().unwrap().clone());
+    stack.push(*stack.last().unwrap());
     Ok(())
 }
 
@@ -57,7 +57,7 @@ pub fn rot(stack: &mut Vec<i64>) -> Result<(), EvalError> {
 pub fn q_dup(stack: &mut Vec<i64>) -> Result<(), EvalError> {
     check_depth!(stack, 1);
     if *stack.last().unwrap() != 0 {
-        stack.push(stack.last().unwrap().clone());
+        stack.push(*stack.last().unwrap());
     }
     Ok(())
 }
@@ -65,7 +65,7 @@ pub fn q_dup(stack: &mut Vec<i64>) -> Result<(), EvalError> {
 // ( n1 n2 -- n1 n2 n1 n2 )
 pub fn two_dup(stack: &mut Vec<i64>) -> Result<(), EvalError> {
     check_depth!(stack, 2);
-    let n2 = stack.last().unwrap().clone();
+    let n2 = *stack.last().unwrap();
     let n1 = stack[stack.len() - 2];
     stack.push(n1);
     stack.push(n2);
@@ -1,13 +1,22 @@
-use logos::Logos;
+use crate::parser::ParseError;
+use logos::{Lexer, Logos};
 use std::fmt;
+use std::rc::Rc;
 
 // Define the error type for lexing
 #[derive(Debug, Clone, PartialEq, Default)] // Added Default
-pub struct LexingError;
+pub enum LexingError {
+    #[default]
+    InvalidCharacter, // Logos's error for text no token matches
+    MalformedNumber(String), // A literal like $FG or 'AB' that looks like a number but is not one
+}
 
 impl fmt::Display for LexingError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Lexing Error")
+        match self {
+            LexingError::InvalidCharacter => write!(f, "Lexing Error"),
+            LexingError::MalformedNumber(text) => write!(f, "Malformed number: {}", text),
+        }
     }
 }
 
@@ -30,16 +39,262 @@ pub enum Token {
     #[token(";")]
     Semicolon,
 
-    // Integer: optional '-' then digits
-    #[regex(r"-?[0-9]+", |lex| lex.slice().parse::<i64>().ok(), priority = 3)]
+    // String words read raw source text up to their closing delimiter
+    #[token(".\"", |lex| parse_text(lex, '"'))]
+    DotQuote(String), // ." text": printed when run
+    #[regex(r#"[Ss]""#, |lex| parse_text(lex, '"').into_bytes())]
+    #[regex(r#"[Ss]\\""#, parse_escaped)]
+    SQuote(Vec<u8>), // S" text" and S\" text" with escapes: ( -- c-addr u )
+    #[regex(r#"[Cc]""#, |lex| parse_text(lex, '"').into_bytes())]
+    CQuote(Vec<u8>), // C" text": ( -- c-addr ) of a counted string
+    #[token(".(", |lex| parse_text(lex, ')'))]
+    DotParen(String), // .( text): printed as soon as it is read
+    #[regex(r#"[Aa][Bb][Oo][Rr][Tt]""#, |lex| parse_text(lex, '"'))]
+    AbortQuote(String), // ABORT" text": aborts with text as the message if a flag is true
+
+    // Numbers with a base prefix ($FF or 0xFF hex, %1010 binary, #123
+    // decimal) and character literals like 'A' mean the same whatever BASE is.
+    // Anything up to the next space belongs to the literal, so $FG is an error
+    // rather than a number followed by a word.
+    #[regex(r"-?(\$|0[xX]|%|#[0-9])[^ \t\r\n\f]*", parse_prefixed, priority = 3)]
+    #[regex(r"'[^ \t\r\n\f]*'", parse_char, priority = 3)]
     Integer(i64),
 
-    // Word: alphanumeric and permitted symbols
-    #[regex(r"[A-Za-z0-9+*/.?=<>-]+", |lex| Some(lex.slice().to_string()), priority = 2)]
+    // Word: alphanumeric and permitted symbols. Plain numbers are words too:
+    // the parser converts them, since their meaning depends on BASE.
+    #[regex(r"[A-Za-z0-9_+*/.?=<>@!,'#\[\]-]+", |lex| Some(lex.slice().to_string()), priority = 2)]
     Word(String),
     // Logos will emit errors for unrecognized chars which are filtered out
 }
 
+// Where a token starts: the name of the file or other source it was read
+// from, and its line and column, both counted from 1
+#[derive(Debug, Clone, PartialEq)]
+pub struct Location {
+    pub source: Rc<str>,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.source, self.line, self.column)
+    }
+}
+
+// Lex source text. Characters no token matches are skipped, but a malformed
+// number is an error, since dropping it would change what the code does. It is
+// reported as ParseError::MalformedNumber, like digits too big for a cell,
+// which only the parser can spot since their value depends on BASE.
+pub fn lex(source: &str) -> Result<Vec<Token>, ParseError> {
+    Ok(lex_located(source, &Rc::from(""), 1)?.0)
+}
+
+// Lex text that starts at `first_line` of `source`, along with the location
+// of each token. Columns count characters, not bytes.
+pub fn lex_located(
+    text: &str,
+    source: &Rc<str>,
+    first_line: usize,
+) -> Result<(Vec<Token>, Vec<Location>), ParseError> {
+    let mut tokens = Vec::new();
+    let mut locations = Vec::new();
+    let (mut line, mut line_start, mut scanned) = (first_line, 0, 0);
+    for (result, span) in Token::lexer(text).spanned() {
+        match result {
+            Ok(token) => {
+                // Count the lines between the last token and this one
+                for (i, c) in text[scanned..span.start].char_indices() {
+                    if c == '\n' {
+                        line += 1;
+                        line_start = scanned + i + 1;
+                    }
+                }
+                scanned = span.start;
+                tokens.push(token);
+                locations.push(Location {
+                    source: source.clone(),
+                    line,
+                    column: text[line_start..span.start].chars().count() + 1,
+                });
+            }
+            Err(LexingError::InvalidCharacter) => {}
+            Err(LexingError::MalformedNumber(text)) => {
+                return Err(ParseError::MalformedNumber(text));
+            }
+        }
+    }
+    Ok((tokens, locations))
+}
+
+// Convert text to a number in `base`: an optional minus sign, then digits,
+// which single underscores may separate (1_000_000). Values up to 2^64 - 1
+// wrap around, so unsigned numbers like FFFFFFFFFFFFFFFF can be written in HEX.
+pub fn parse_number(text: &str, base: u32) -> Option<i64> {
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, text),
+    };
+    let value = parse_digits(digits, base)? as i64;
+    Some(if negative {
+        value.wrapping_neg()
+    } else {
+        value
+    })
+}
+
+// What a word means as a number
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Number {
+    Cell(i64),
+    TooBig,     // Only digits, but more than a cell holds: a malformed number
+    NotANumber, // Anything else, which can only be a word
+}
+
+// Read text as a number in `base` like parse_number, but tell digits too big
+// for a cell apart from text that is not a number at all
+pub fn read_number(text: &str, base: u32) -> Number {
+    let digits = text.strip_prefix('-').unwrap_or(text);
+    match parse_number(text, base) {
+        Some(n) => Number::Cell(n),
+        None if well_formed(digits) && digits.chars().all(|c| c == '_' || c.is_digit(base)) => {
+            Number::TooBig
+        }
+        None => Number::NotANumber,
+    }
+}
+
+// A separator goes between two digits
+fn well_formed(digits: &str) -> bool {
+    !(digits.is_empty()
+        || digits.starts_with('_')
+        || digits.ends_with('_')
+        || digits.contains("__"))
+}
+
+fn parse_digits(digits: &str, base: u32) -> Option<u64> {
+    if !well_formed(digits) {
+        return None;
+    }
+    let mut value: u64 = 0;
+    for c in digits.chars().filter(|&c| c != '_') {
+        let digit = c.to_digit(base)?;
+        value = value.checked_mul(base as u64)?.checked_add(digit as u64)?;
+    }
+    Some(value)
+}
+
+fn parse_prefixed(lex: &mut Lexer<Token>) -> Result<i64, LexingError> {
+    let text = lex.slice();
+    let (negative, rest) = match text.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, text),
+    };
+    let (base, digits) = if let Some(digits) = rest.strip_prefix('$') {
+        (16, digits)
+    } else if let Some(digits) = rest.strip_prefix("0x").or(rest.strip_prefix("0X")) {
+        (16, digits)
+    } else if let Some(digits) = rest.strip_prefix('%') {
+        (2, digits)
+    } else {
+        (10, &rest[1..]) // After the #
+    };
+    let value = parse_digits(digits, base)
+        .ok_or_else(|| LexingError::MalformedNumber(text.to_string()))? as i64;
+    Ok(if negative {
+        value.wrapping_neg()
+    } else {
+        value
+    })
+}
+
+// 'A' is the character code of A. The quotes hold exactly one character.
+fn parse_char(lex: &mut Lexer<Token>) -> Result<i64, LexingError> {
+    let text = lex.slice();
+    let mut chars = text[1..text.len() - 1].chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Ok(c as i64),
+        _ => Err(LexingError::MalformedNumber(text.to_string())),
+    }
+}
+
+// The text after a string word, up to `delimiter`. A single space separates
+// the word from its text; without a closing delimiter the text runs to the end
+// of the line.
+fn parse_text(lex: &mut Lexer<Token>, delimiter: char) -> String {
+    let rest = lex.remainder();
+    let line = &rest[..rest.find('\n').unwrap_or(rest.len())];
+    let start = usize::from(line.starts_with([' ', '\t']));
+    let (text, consumed) = match line[start..].find(delimiter) {
+        Some(end) => (&line[start..start + end], start + end + 1),
+        None => (&line[start..], line.len()),
+    };
+    lex.bump(consumed);
+    text.to_string()
+}
+
+// Like parse_text for S\", translating backslash escapes such as \n, \" and
+// \x41 as it goes
+fn parse_escaped(lex: &mut Lexer<Token>) -> Vec<u8> {
+    let rest = lex.remainder();
+    let mut chars = rest.char_indices().peekable();
+    if let Some((_, ' ' | '\t')) = chars.peek() {
+        chars.next();
+    }
+    let mut bytes = Vec::new();
+    let mut consumed = rest.len();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => {
+                consumed = i + 1;
+                break;
+            }
+            '\n' => {
+                consumed = i;
+                break;
+            }
+            '\\' => match chars.peek().copied() {
+                Some((_, '\n')) | None => {}
+                Some((_, escape)) => {
+                    chars.next();
+                    match escape {
+                        'a' => bytes.push(7),
+                        'b' => bytes.push(8),
+                        'e' => bytes.push(27),
+                        'f' => bytes.push(12),
+                        'l' | 'n' => bytes.push(b'\n'),
+                        'm' => bytes.extend(b"\r\n"),
+                        'q' => bytes.push(b'"'),
+                        'r' => bytes.push(b'\r'),
+                        't' => bytes.push(b'\t'),
+                        'v' => bytes.push(11),
+                        'z' => bytes.push(0),
+                        'x' => {
+                            // Two hex digits; fewer give what was read so far
+                            let mut byte = 0;
+                            for _ in 0..2 {
+                                match chars.peek().and_then(|&(_, d)| d.to_digit(16)) {
+                                    Some(digit) => {
+                                        byte = byte * 16 + digit as u8;
+                                        chars.next();
+                                    }
+                                    None => break,
+                                }
+                            }
+                            bytes.push(byte);
+                        }
+                        // \" and \\ stand for themselves, like any other character
+                        other => bytes.extend(other.to_string().bytes()),
+                    }
+                }
+            },
+            c => bytes.extend(c.to_string().bytes()),
+        }
+    }
+    lex.bump(consumed);
+    bytes
+}
+
 impl fmt::Display for Token {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
@@ -47,6 +302,11 @@ impl fmt::Display for Token {
             Token::Word(s) => write!(f, "{}", s),
             Token::Colon => write!(f, ":"),
             Token::Semicolon => write!(f, ";"),
+            Token::DotQuote(text) => write!(f, ".\" {}\"", text),
+            Token::SQuote(bytes) => write!(f, "S\" {}\"", String::from_utf8_lossy(bytes)),
+            Token::CQuote(bytes) => write!(f, "C\" {}\"", String::from_utf8_lossy(bytes)),
+            Token::DotParen(text) => write!(f, ".( {})", text),
+            Token::AbortQuote(text) => write!(f, "ABORT\" {}\"", text),
             Token::Whitespace => write!(f, " "), // Should ideally not be displayed directly
             Token::Comment => write!(f, "(comment)"), // Should ideally not be displayed directly
             Token::LineComment => write!(f, "\\\\ comment"), // Should ideally not be displayed directly
@@ -91,7 +351,7 @@ mod tests {
 
     #[test]
     fn test_lex_number_word_mix() {
-        // Should be Word("1abc") not Integer(1), Word("abc")
+        // Should be Word("1abc") not Word("1"), Word("abc")
         assert_eq!(lex_string("1abc"), vec![Token::Word("1abc".to_string())]);
         // Should be Word("a1b2")
         assert_eq!(lex_string("a1b2"), vec![Token::Word("a1b2".to_string())]);
@@ -99,11 +359,12 @@ mod tests {
 
     #[test]
     fn test_lex_pure_number() {
-        assert_eq!(lex_string("123"), vec![Token::Integer(123)]);
-        assert_eq!(lex_string("-45"), vec![Token::Integer(-45)]);
-        // Ensure leading zero doesn't make it a word if it's just a number
-        assert_eq!(lex_string("0"), vec![Token::Integer(0)]);
-        assert_eq!(lex_string("-0"), vec![Token::Integer(0)]);
+        // Numbers are left to the parser, which knows the current BASE
+        assert_eq!(lex_string("123"), vec![Token::Word("123".to_string())]);
+        assert_eq!(lex_string("-45"), vec![Token::Word("-45".to_string())]);
+        assert_eq!(lex_string("0"), vec![Token::Word("0".to_string())]);
+        assert_eq!(lex_string("-0"), vec![Token::Word("-0".to_string())]);
+        assert_eq!(lex_string("FF"), vec![Token::Word("FF".to_string())]);
     }
 
     #[test]
@@ -126,7 +387,7 @@ mod tests {
         assert_eq!(
             lex_string("10 sq3 + : foo ;"),
             vec![
-                Token::Integer(10),
+                Token::Word("10".to_string()),
                 Token::Word("sq3".to_string()), // Correctly lexed as one word
                 Token::Word("+".to_string()),
                 Token::Colon,
@@ -142,6 +403,39 @@ mod tests {
         assert_eq!(lex_string(".s"), vec![Token::Word(".s".to_string())]); // Ensure existing words still work
         assert_eq!(lex_string("."), vec![Token::Word(".".to_string())]);
         assert_eq!(lex_string("+"), vec![Token::Word("+".to_string())]);
+        assert_eq!(lex_string("@"), vec![Token::Word("@".to_string())]);
+        assert_eq!(
+            lex_string("SENSOR@ LED!"),
+            vec![
+                Token::Word("SENSOR@".to_string()),
+                Token::Word("LED!".to_string())
+            ]
+        );
+        assert_eq!(
+            lex_string(", C, 1,"),
+            vec![
+                Token::Word(",".to_string()),
+                Token::Word("C,".to_string()),
+                Token::Word("1,".to_string())
+            ]
+        );
+        assert_eq!(
+            lex_string("<# # #S #>"),
+            vec![
+                Token::Word("<#".to_string()),
+                Token::Word("#".to_string()),
+                Token::Word("#S".to_string()),
+                Token::Word("#>".to_string())
+            ]
+        );
+        assert_eq!(
+            lex_string("' ['] ACTION-OF"),
+            vec![
+                Token::Word("'".to_string()),
+                Token::Word("[']".to_string()),
+                Token::Word("ACTION-OF".to_string())
+            ]
+        );
     }
 
     #[test]
@@ -152,7 +446,7 @@ mod tests {
         // But "1 +" should be two tokens
         assert_eq!(
             lex_string("1 +"),
-            vec![Token::Integer(1), Token::Word("+".to_string())]
+            vec![Token::Word("1".to_string()), Token::Word("+".to_string())]
         );
     }
 
@@ -191,45 +485,155 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_lex_prefixed_numbers() {
+        assert_eq!(
+            lex_string("$FF 0xff 0X10 %1010 #123 -$10 -#5 -%1"),
+            vec![
+                Token::Integer(255),
+                Token::Integer(255),
+                Token::Integer(16),
+                Token::Integer(10),
+                Token::Integer(123),
+                Token::Integer(-16),
+                Token::Integer(-5),
+                Token::Integer(-1),
+            ]
+        );
+        assert_eq!(
+            lex_string("$FFFF_FFFF %1111_0000 #1_000"),
+            vec![
+                Token::Integer(0xFFFF_FFFF),
+                Token::Integer(0b1111_0000),
+                Token::Integer(1000)
+            ]
+        );
+        assert_eq!(lex_string("$FFFFFFFFFFFFFFFF"), vec![Token::Integer(-1)]);
+        // The pictured output words are not decimal literals
+        assert_eq!(
+            lex_string("# #s #>"),
+            vec![
+                Token::Word("#".to_string()),
+                Token::Word("#s".to_string()),
+                Token::Word("#>".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lex_char_literals() {
+        assert_eq!(
+            lex_string("'A' '0' ''' ' [']"),
+            vec![
+                Token::Integer(65),
+                Token::Integer(48),
+                Token::Integer(39),
+                Token::Word("'".to_string()),
+                Token::Word("[']".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lex_malformed_numbers() {
+        for text in [
+            "$",
+            "$FG",
+            "0x",
+            "0x1.5",
+            "%102",
+            "#12a",
+            "$_1",
+            "$1_",
+            "$1__0",
+            "''",
+            "'AB'",
+            "$10000000000000000",
+        ] {
+            assert_eq!(
+                lex_string_results(text),
+                vec![Err(LexingError::MalformedNumber(text.to_string()))],
+                "{}",
+                text
+            );
+        }
+        assert_eq!(
+            lex("1 $FG 2"),
+            Err(ParseError::MalformedNumber("$FG".to_string()))
+        );
+        // Stray characters are still skipped
+        assert_eq!(
+            lex("1 ^ $2"),
+            Ok(vec![Token::Word("1".to_string()), Token::Integer(2)])
+        );
+    }
+
+    #[test]
+    fn test_read_number_errors() {
+        // Digits that do not fit in a cell make a malformed number
+        for text in ["99999999999999999999", "-99999999999999999999"] {
+            assert_eq!(read_number(text, 10), Number::TooBig, "{}", text);
+        }
+        assert_eq!(read_number("10000000000000000", 16), Number::TooBig);
+        // Anything else that is not a number may be a word
+        for text in ["12a", "-", "1-", "_1", "1__0", "$10"] {
+            assert_eq!(read_number(text, 10), Number::NotANumber, "{}", text);
+        }
+        assert_eq!(read_number("-42", 10), Number::Cell(-42));
+    }
+
+    #[test]
+    fn test_parse_number() {
+        assert_eq!(parse_number("42", 10), Some(42));
+        assert_eq!(parse_number("-42", 10), Some(-42));
+        assert_eq!(parse_number("ff", 16), Some(255));
+        assert_eq!(parse_number("-1010", 2), Some(-10));
+        assert_eq!(parse_number("1_000_000", 10), Some(1_000_000));
+        assert_eq!(parse_number("FFFFFFFFFFFFFFFF", 16), Some(-1));
+        assert_eq!(parse_number("10000000000000000", 16), None);
+        assert_eq!(parse_number("12", 2), None);
+        assert_eq!(parse_number("-", 10), None);
+        assert_eq!(parse_number("1-", 10), None);
+        assert_eq!(parse_number("_1", 10), None);
+        assert_eq!(parse_number("1__0", 10), None);
+    }
+
     #[test]
     fn test_lex_error_handling() {
         // First, test the basic case with the filtered lexer function
-        assert_eq!(lex_string("#$%"), Vec::<Token>::new());
+        assert_eq!(lex_string("^&~"), Vec::<Token>::new());
 
         // Now test with the unfiltered lexer function to capture errors
-        let results = lex_string_results("#");
+        let results = lex_string_results("^");
         assert!(!results.is_empty(), "Should have at least one result");
-        assert!(
-            matches!(results[0], Err(_)),
-            "First result should be an error"
-        );
+        assert!(results[0].is_err(), "First result should be an error");
 
         // Test with multiple invalid characters
-        let results = lex_string_results("#$%");
+        let results = lex_string_results("^&~");
         assert_eq!(
             results.len(),
             3,
             "Should have three results for three invalid chars"
         );
         assert!(
-            results.iter().all(|r| matches!(r, Err(_))),
+            results.iter().all(|r| r.is_err()),
             "All results should be errors"
         );
 
         // Test mixed valid and invalid input
-        let results = lex_string_results("123 # abc");
+        let results = lex_string_results("123 ^ abc");
         // Whitespace is skipped by the lexer, so we only get 3 tokens/errors
         assert_eq!(
             results.len(),
             3,
-            "Should have 3 tokens/errors: '123', '#', 'abc'"
+            "Should have 3 tokens/errors: '123', '^', 'abc'"
         );
 
-        // The first token should be Ok(Integer(123))
-        assert!(matches!(results[0], Ok(Token::Integer(123))));
+        // The first token should be Ok(Word("123"))
+        assert!(matches!(results[0], Ok(Token::Word(ref s)) if s == "123"));
 
-        // The '#' should be an error
-        assert!(matches!(results[1], Err(_)));
+        // The '^' should be an error
+        assert!(results[1].is_err());
 
         // The 'abc' should be Ok(Word("abc"))
         assert!(matches!(results[2], Ok(Token::Word(ref s)) if s == "abc"));
@@ -244,11 +648,11 @@ mod tests {
         assert_eq!(
             tokens,
             vec![
-                Token::Integer(10),
-                Token::Integer(20),
+                Token::Word("10".to_string()),
+                Token::Word("20".to_string()),
                 Token::Word("+".to_string()),
                 Token::Word(".s".to_string()),
-                Token::Integer(-5),
+                Token::Word("-5".to_string()),
                 Token::Word("*".to_string()),
                 Token::Word(".".to_string()),
             ]
@@ -278,11 +682,11 @@ mod tests {
         assert_eq!(
             tokens,
             vec![
-                Token::Integer(1),
-                Token::Integer(2),
+                Token::Word("1".to_string()),
+                Token::Word("2".to_string()),
                 Token::Word("swap".to_string()),
-                Token::Integer(3),
-                Token::Integer(4),
+                Token::Word("3".to_string()),
+                Token::Word("4".to_string()),
                 Token::Word("drop".to_string()),
             ]
         );
@@ -290,9 +694,12 @@ mod tests {
 
     #[test]
     fn test_lexer_skips_unknown() {
-        let input = "1 #$% 2"; // #$% should be skipped
+        let input = "1 ^&~ 2"; // ^&~ should be skipped
         let tokens: Vec<Token> = Token::lexer(input).filter_map(Result::ok).collect();
-        assert_eq!(tokens, vec![Token::Integer(1), Token::Integer(2)]);
+        assert_eq!(
+            tokens,
+            vec![Token::Word("1".to_string()), Token::Word("2".to_string())]
+        );
     }
 
     #[test]
@@ -318,10 +725,10 @@ mod tests {
         assert_eq!(
             tokens,
             vec![
-                Token::Integer(10),
+                Token::Word("10".to_string()),
                 Token::Colon,
                 Token::Word("DOUBLE".to_string()),
-                Token::Integer(2),
+                Token::Word("2".to_string()),
                 Token::Word("*".to_string()),
                 Token::Semicolon,
                 Token::Word("DOUBLE".to_string()),
@@ -329,4 +736,86 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn test_lex_strings() {
+        assert_eq!(
+            lex_string(r#"." Hello, world!" s" abc" C" x y" .( note) 1"#),
+            vec![
+                Token::DotQuote("Hello, world!".to_string()),
+                Token::SQuote(b"abc".to_vec()),
+                Token::CQuote(b"x y".to_vec()),
+                Token::DotParen("note".to_string()),
+                Token::Word("1".to_string()),
+            ]
+        );
+        // Only the first space belongs to the delimiter
+        assert_eq!(
+            lex_string(r#"."  two" ." ""#),
+            vec![
+                Token::DotQuote(" two".to_string()),
+                Token::DotQuote(String::new()),
+            ]
+        );
+        // An unterminated string ends with its line
+        assert_eq!(
+            lex_string(".\" open\n2"),
+            vec![
+                Token::DotQuote("open".to_string()),
+                Token::Word("2".to_string())
+            ]
+        );
+        // Comments and words with dots are unaffected
+        assert_eq!(
+            lex_string(". ( comment ) .s"),
+            vec![Token::Word(".".to_string()), Token::Word(".s".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_lex_abort_quote() {
+        assert_eq!(
+            lex_string(r#"abort" bad input" ABORT" x" abort"#),
+            vec![
+                Token::AbortQuote("bad input".to_string()),
+                Token::AbortQuote("x".to_string()),
+                Token::Word("abort".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lex_located() {
+        let source: Rc<str> = Rc::from("test.fth");
+        let (tokens, locations) = lex_located(": sq ( n -- n2 )\n  dup *  ;", &source, 3).unwrap();
+        assert_eq!(tokens.len(), 5);
+        let at = |line, column| Location {
+            source: source.clone(),
+            line,
+            column,
+        };
+        assert_eq!(
+            locations,
+            vec![at(3, 1), at(3, 3), at(4, 3), at(4, 7), at(4, 10)]
+        );
+        assert_eq!(locations[2].to_string(), "test.fth:4:3");
+        // Columns count characters
+        let (_, locations) = lex_located(".\" é\" x", &source, 1).unwrap();
+        assert_eq!(locations[1].column, 7);
+    }
+
+    #[test]
+    fn test_lex_escaped_string() {
+        assert_eq!(
+            lex_string(r#"S\" a\tb\n\q\"\\\x41\x7e\m" 1"#),
+            vec![
+                Token::SQuote(b"a\tb\n\"\"\\A~\r\n".to_vec()),
+                Token::Word("1".to_string())
+            ]
+        );
+        assert_eq!(
+            lex_string(r#"s\" \z\e\xg""#),
+            vec![Token::SQuote(vec![0, 27, 0, b'g'])]
+        );
+    }
 }